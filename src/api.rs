@@ -2,20 +2,18 @@
 // SPDX-License-Identifier: GPL-3.0/
 
 use std::env;
-use libc::option;
 use whoami::username;
-use iridescent::{Styled, Rgb};
+use iridescent::Styled;
 use git2::Repository;
 use crate::cmd_runner;
-use crate::cmd_runner::aliases;
-use crate::rhai_api::{self, init_rhai};
+use crate::cmd_runner::{aliases, capture, frecency, history, options, read, timing};
+use crate::editor::{completion, fuzzy, highlight, keys, segments};
+use crate::rhai_api::init_rhai;
+use rhai::{Array, Dynamic, EvalAltResult, FnPtr, Map, NativeCallContext};
 use std::fs;
-use std::io::Write;
 use std::path::Path;
 use chrono::Local;
-use std::time::Instant;
-use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use once_cell::sync::Lazy;
 use std::sync::Mutex;
 static GLOBAL_TIMER: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
@@ -37,22 +35,50 @@ pub fn alias_add(name: &str, command: &str) {
     aliases::add(name, command);
 }
 
-pub fn alias_get(name: &str) -> Option<String> {
-    aliases::get(name)
+pub fn alias_add_global(name: &str, command: &str) {
+    aliases::add_global(name, command);
+}
+
+pub fn alias_get(name: &str) -> Dynamic {
+    aliases::get(name).or_else(|| aliases::get_global(name)).map(Dynamic::from).unwrap_or(Dynamic::UNIT)
 }
 
 pub fn alias_remove(name: &str) {
     aliases::remove(name);
 }
 
-pub fn alias_list() {
-    aliases::list();
+fn to_map(pairs: Vec<(String, String)>) -> Map {
+    pairs.into_iter().map(|(name, value)| (name.into(), value.into())).collect()
+}
+
+pub fn alias_list() -> Map {
+    to_map(aliases::list())
+}
+
+pub fn alias_list_global() -> Map {
+    to_map(aliases::list_global())
 }
 
 pub fn alias_clear() {
     aliases::clear();
 }
 
+pub fn abbr_add(name: &str, expansion: &str) {
+    aliases::add_abbreviation(name, expansion);
+}
+
+pub fn abbr_get(name: &str) -> Dynamic {
+    aliases::abbreviation(name).map(Dynamic::from).unwrap_or(Dynamic::UNIT)
+}
+
+pub fn abbr_remove(name: &str) {
+    aliases::remove_abbreviation(name);
+}
+
+pub fn abbr_list() -> Map {
+    to_map(aliases::list_abbreviations())
+}
+
 //Variables
 pub fn set_var(name: String, value: String) {
     unsafe {env::set_var(name, value);}
@@ -69,6 +95,178 @@ pub fn del_var(name: String){
 pub fn run_command(command: String){
     cmd_runner::handle_builtin(&command);
 }
+
+/// run_command(line): runs a command with its output captured
+pub fn capture_command(line: String) -> Map {
+    let options = capture::Options::default();
+    run_captured(&line, &options)
+}
+
+/// run_command(line, #{stdin, env, cwd, timeout}); a bad env entry is an error, raised before forking
+pub fn capture_command_with(line: String, opts: Map) -> Result<Map, Box<EvalAltResult>> {
+    let mut options = capture::Options::default();
+    for (key, value) in opts {
+        match key.as_str() {
+            "stdin" => options.stdin = Some(value.to_string()),
+            "cwd" => options.cwd = Some(value.to_string()),
+            "env" => match value.try_cast::<Map>() {
+                Some(env) => {
+                    for (name, value) in env {
+                        let value = value.to_string();
+                        // set_var in the child would panic on these
+                        if name.is_empty() || name.contains(['=', '\0']) || value.contains('\0') {
+                            return Err(format!("run_command: invalid variable in env: '{}'", name).into());
+                        }
+                        options.env.push((name.to_string(), value));
+                    }
+                }
+                None => eprintln!("run_command: env must be a map"),
+            },
+            "timeout" => match value.as_float().ok().or_else(|| value.as_int().ok().map(|secs| secs as f64)) {
+                Some(secs) if secs >= 0.0 => options.timeout = Some(Duration::from_secs_f64(secs)),
+                _ => eprintln!("run_command: timeout must be a number of seconds"),
+            },
+            other => eprintln!("run_command: unknown option {}", other),
+        }
+    }
+    Ok(run_captured(&line, &options))
+}
+
+fn run_captured(line: &str, options: &capture::Options) -> Map {
+    let mut result = Map::new();
+    let (out, err, code, duration) = match capture::run(line, options) {
+        Ok(output) => (output.out, output.err, output.code, output.duration.as_secs_f64()),
+        Err(e) => (String::new(), format!("sbsh: {}\n", e), 1, 0.0),
+    };
+    result.insert("out".into(), out.into());
+    result.insert("err".into(), err.into());
+    result.insert("code".into(), (code as i64).into());
+    result.insert("duration".into(), duration.into());
+    result
+}
+//Shell options
+pub fn set_option(name: String, value: bool) {
+    if !options::set(&name, value) {
+        eprintln!("set_option: no such option: {}", name);
+    }
+}
+
+pub fn get_option(name: String) -> bool {
+    options::get(&name)
+}
+//History
+pub fn expand_history(line: &str) -> Result<Option<String>, String> {
+    if !options::get("histexpand") {
+        return Ok(None);
+    }
+    history::expand(line)
+}
+
+pub fn add_history_secret(pattern: String) {
+    if let Err(e) = history::add_secret(&pattern) {
+        eprintln!("add_history_secret: {}", e);
+    }
+}
+
+pub fn clear_history_secrets() {
+    history::clear_secrets();
+}
+//Input
+pub fn prompt_input(message: String) -> String {
+    let options = read::ReadOptions { prompt: message, raw: true, ..Default::default() };
+    read::read_input(&options).unwrap_or_default()
+}
+//Directory jumping
+pub fn dir_jump(query: String) -> Array {
+    let terms: Vec<String> = query.split_whitespace().map(String::from).collect();
+    frecency::query(&terms).into_iter().map(|(path, _)| path.into()).collect()
+}
+//Completion
+pub fn complete(command: String, callback: FnPtr) {
+    completion::register(command, callback);
+}
+
+pub fn complete_files(prefix: String) -> Array {
+    completion::list_paths(&prefix, false).into_iter().map(|p| p.into()).collect()
+}
+
+pub fn complete_dirs(prefix: String) -> Array {
+    completion::list_paths(&prefix, true).into_iter().map(|p| p.into()).collect()
+}
+
+pub fn complete_options(prefix: String, options: Array) -> Array {
+    options.into_iter()
+        .filter(|o| o.to_string().starts_with(&prefix))
+        .collect()
+}
+
+/// Runs `callback` at most once per `seconds` for the same key and returns its list
+pub fn complete_cache(ctx: NativeCallContext, key: String, seconds: i64, callback: FnPtr) -> Result<Array, Box<EvalAltResult>> {
+    let max_age = Duration::from_secs(seconds.max(0) as u64);
+    if let Some(value) = completion::cached(&key, max_age) {
+        return Ok(value);
+    }
+    let value: Array = callback.call_within_context(&ctx, ())?;
+    completion::store(key, value.clone());
+    Ok(value)
+}
+//Line editor settings, used when the editor is created after the config has run
+pub fn set_edit_mode(mode: String) {
+    match keys::parse_edit_mode(&mode) {
+        Some(m) => keys::SETTINGS.lock().unwrap().edit_mode = m,
+        None => eprintln!("set_edit_mode: unknown mode {} (use \"emacs\" or \"vi\")", mode),
+    }
+}
+
+pub fn set_bell_style(style: String) {
+    match keys::parse_bell_style(&style) {
+        Some(b) => keys::SETTINGS.lock().unwrap().bell_style = b,
+        None => eprintln!("set_bell_style: unknown style {} (use \"audible\", \"visible\" or \"none\")", style),
+    }
+}
+
+pub fn set_completion_type(kind: String) {
+    match keys::parse_completion_type(&kind) {
+        Some(c) => keys::SETTINGS.lock().unwrap().completion_type = c,
+        None => eprintln!("set_completion_type: unknown type {} (use \"list\" or \"circular\")", kind),
+    }
+}
+
+pub fn set_history_size(size: i64) {
+    keys::SETTINGS.lock().unwrap().history_size = size.max(1) as usize;
+}
+
+pub fn set_history_ignore_dups(value: bool) {
+    keys::SETTINGS.lock().unwrap().history_ignore_dups = value;
+}
+
+pub fn bind_key(key: String, callback: FnPtr) {
+    match keys::parse_key(&key) {
+        Some(k) => keys::bind(k, keys::Action::Call(callback)),
+        None => eprintln!("bind_key: unknown key {}", key),
+    }
+}
+
+pub fn bind_key_text(key: String, text: String) {
+    match keys::parse_key(&key) {
+        Some(k) => keys::bind(k, keys::Action::Insert(text)),
+        None => eprintln!("bind_key: unknown key {}", key),
+    }
+}
+//Fuzzy finder
+/// The item chosen in the fuzzy finder, () if cancelled
+pub fn fuzzy_select(list: Array) -> Dynamic {
+    let items: Vec<String> = list.into_iter().map(|item| item.to_string()).collect();
+    fuzzy::select(&items, "> ").map(Dynamic::from).unwrap_or(Dynamic::UNIT)
+}
+
+pub fn history_list() -> Array {
+    fuzzy::history_items().into_iter().map(|item| item.into()).collect()
+}
+
+pub fn file_list() -> Array {
+    fuzzy::file_items().into_iter().map(|item| item.into()).collect()
+}
 //Fromating
 pub fn set_color(text: String, r: i64, g: i64, b: i64) -> String {
     text.foreground(&[r as u8, g as u8, b as u8]).to_string()
@@ -78,6 +276,12 @@ pub fn set_color(text: String, r: i64, g: i64, b: i64) -> String {
 pub fn set_bold(text: String) -> String {
     text.bold().to_string()
 }
+
+pub fn set_highlight(kind: String, r: i64, g: i64, b: i64) {
+    if !highlight::set_color(&kind, [r as u8, g as u8, b as u8]) {
+        eprintln!("set_highlight: unknown kind {}", kind);
+    }
+}
 //Git
 pub fn is_git_repo() -> bool {
     env::current_dir()
@@ -127,7 +331,11 @@ pub fn git_ahead_behind() -> (usize, usize) {
         Ok(r) => r,
         Err(_) => return (0, 0),
     };
+    ahead_behind(&repo)
+}
 
+/// Commits the current branch of `repo` is ahead of and behind its upstream
+pub fn ahead_behind(repo: &Repository) -> (usize, usize) {
     let head = match repo.head() {
         Ok(h) => h,
         Err(_) => return (0, 0),
@@ -159,13 +367,36 @@ pub fn git_ahead_behind() -> (usize, usize) {
     };
 
     match repo.graph_ahead_behind(local_oid, upstream_oid) {
-        Ok((ahead, behind)) => (ahead as usize, behind as usize),
+        Ok((ahead, behind)) => (ahead, behind),
         Err(_) => (0, 0),
     }
 }
+
+//Async prompt segments
+pub fn git_status_async() -> Dynamic {
+    let Some(status) = segments::git_status() else {
+        return Dynamic::UNIT;
+    };
+    let mut map = Map::new();
+    map.insert("branch".into(), status.branch.into());
+    map.insert("dirty".into(), status.dirty.into());
+    map.insert("ahead".into(), (status.ahead as i64).into());
+    map.insert("behind".into(), (status.behind as i64).into());
+    map.into()
+}
+
+pub fn add_prompt_segment(name: String, command: String) {
+    segments::add_command(&name, &command);
+}
+
+pub fn prompt_segment(name: String) -> Dynamic {
+    segments::command_output(&name).map(Dynamic::from).unwrap_or(Dynamic::UNIT)
+}
 pub fn load_plugin(path: String){
     let engine = init_rhai();
-    engine.run_file(path.into());
+    if let Err(e) = engine.run_file(path.clone().into()) {
+        eprintln!("Error in plugin {}: {}", path, e);
+    }
 }
 
 //File API
@@ -198,12 +429,7 @@ pub fn is_dir(path_str: &str) -> bool {
 pub fn is_file(path_str: &str) -> bool {
     let path = Path::new(path_str);
     if let Ok(metadata) = fs::metadata(path) {
-        if metadata.is_dir() == true{
-            false
-        }
-        else {
-            true
-        }
+        !metadata.is_dir()
     } else {
         false
     }
@@ -228,4 +454,66 @@ pub fn stop_timer() -> f64 {
     } else {
         0.0 // Возвращаем 0, если забыли вызвать start_timer
     }
+}
+
+//Named timers
+pub fn start_named_timer(name: String) {
+    timing::start_timer(&name);
+}
+
+pub fn stop_named_timer(name: String) -> f64 {
+    match timing::stop_timer(&name) {
+        Some(elapsed) => elapsed.as_secs_f64(),
+        None => {
+            eprintln!("stop_timer: no timer named {}", name);
+            0.0
+        }
+    }
+}
+
+pub fn lap_timer(name: String) -> f64 {
+    match timing::lap_timer(&name) {
+        Some(lap) => lap.as_secs_f64(),
+        None => {
+            eprintln!("lap_timer: no timer named {}", name);
+            0.0
+        }
+    }
+}
+
+pub fn reset_timer(name: String) {
+    if !timing::reset_timer(&name) {
+        eprintln!("reset_timer: no timer named {}", name);
+    }
+}
+
+pub fn list_timers() -> Map {
+    timing::timers().into_iter()
+        .map(|(name, elapsed)| (name.into(), elapsed.as_secs_f64().into()))
+        .collect()
+}
+
+pub fn format_duration(secs: f64) -> String {
+    timing::format_duration(secs)
+}
+
+pub fn format_duration_int(secs: i64) -> String {
+    timing::format_duration(secs as f64)
+}
+
+//Last command
+pub fn last_duration() -> f64 {
+    timing::last().duration.as_secs_f64()
+}
+
+pub fn last_cpu_time() -> f64 {
+    timing::last().cpu.as_secs_f64()
+}
+
+pub fn last_status() -> i64 {
+    timing::last().status as i64
+}
+
+pub fn last_command() -> String {
+    timing::last().command
 }
\ No newline at end of file
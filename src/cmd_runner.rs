@@ -1,122 +1,293 @@
 // Copyright (C) <2026> <Bogdan Yachmenv>
 // SPDX-License-Identifier: GPL-3.0/
 
-use libc::{WEXITSTATUS, WIFEXITED, waitpid, fork, execvp};
+use libc::{WEXITSTATUS, WIFEXITED, WIFSIGNALED, WTERMSIG, fork, execvp};
 use shlex::split;
+use std::env;
 use std::ffi::CString;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
 use std::ptr;
-use std::env;
-use std::path::Path;
-use std::io::{self, Write};
+use std::io::Write;
+use std::sync::atomic::{AtomicI32, Ordering};
 pub mod aliases;
+pub mod capture;
+mod conditions;
+pub mod dirs;
+pub mod frecency;
+pub mod history;
+pub mod options;
+pub mod pipeline;
 mod print;
+pub mod read;
 mod small_utils;
+pub mod timing;
 mod var;
-use crate::GLOBAL_AST;
-use crate::GLOBAL_ENGINE;
 
-pub fn handle_builtin(line: &str) {
+/// Names handled by run_builtin
+pub const BUILTINS: &[&str] = &["print", "cd", "var", "exit", "clr", "update", "set", "setopt", "unsetopt",
+    "test", "[", "[[", "read", "echo", "printf",
+    "pushd", "popd", "dirs", "z", "history", "alias", "unalias", "abbr"];
+
+static LAST_STATUS: AtomicI32 = AtomicI32::new(0);
+
+/// Exit status of the last command ($?)
+pub fn last_status() -> i32 {
+    LAST_STATUS.load(Ordering::Relaxed)
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.contains(&name)
+}
+
+pub fn handle_builtin(line: &str) -> i32 {
+    let status = dispatch(line);
+    LAST_STATUS.store(status, Ordering::Relaxed);
+
+    if status != 0 && options::get("errexit") {
+        small_utils::exit(vec!["exit".to_string(), status.to_string()]);
+    }
+    status
+}
+
+fn dispatch(line: &str) -> i32 {
     const MAX_EXPANSION: u32 = 10;  // защита от циклических алиасов
-    let mut current_line = line.to_string();
+    let mut current_line = aliases::expand_globals(line);
     let mut expansion_count = 0;
+    // An alias is not expanded again inside its own expansion (alias ls='ls -F')
+    let mut expanded_names: Vec<String> = Vec::new();
 
     loop {
         let args: Vec<String> = match split(&current_line) {
             Some(a) => a,
             None => {
                 eprintln!("Command parsing error");
-                return;
+                return 2;
             }
         };
         if args.is_empty() {
-            return;
+            return 0;
         }
 
-        if let Some(alias_cmd) = aliases::get(&args[0]) {
+        if !expanded_names.contains(&args[0])
+            && let Some(alias_cmd) = aliases::get(&args[0]) {
             if expansion_count >= MAX_EXPANSION {
                 eprintln!("Alias expansion too deep (possible cycle)");
-                return;
+                return 1;
             }
-            let rest = if args.len() > 1 {
-                format!(" {}", args[1..].join(" "))
-            } else {
-                String::new()
-            };
-            current_line = format!("{}{}", alias_cmd, rest);
+            // The arguments go back as typed, so their quotes still apply
+            let raw = pipeline::raw_words(&current_line);
+            current_line = aliases::expand(&alias_cmd, raw.get(1..).unwrap_or_default());
+            expanded_names.push(args[0].clone());
             expansion_count += 1;
             continue;
         }
+        break;
+    }
 
-        match args[0].as_str() {
-            "print" => {
-                print::print(args);
-            }
-            "cd" => {
-                small_utils::cd(args);
-            }
-            "var" => {
-                var::handle_var(args);
-            }
-            "exit" => {
-                small_utils::exit(args);
-            }
-            "clr" => {
-                small_utils::clr();
-            }
-            "update" =>{
-                crate::GLOBAL_ENGINE.with(|eng| {
-                crate::GLOBAL_AST.with(|cell| {
-                if let Some(ast) = cell.borrow().as_ref() {
-                    let engine = eng.borrow();
-                    let mut scope = rhai::Scope::new();
-                    if let Err(e) = engine.run_ast(ast) {
-                        if !e.to_string().contains("Function not found") {
-                            eprintln!("Error in on_cd hook: {}", e);
-                        }
-                    }
+    let expanded = match var::expand(&current_line) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("sbsh: {}", e);
+            return 1;
+        }
+    };
+    let mut args: Vec<String> = match split(&expanded) {
+        Some(a) if !a.is_empty() => a,
+        Some(_) => return 0,
+        None => {
+            eprintln!("Command parsing error");
+            return 2;
+        }
+    };
+
+    let raw = pipeline::raw_words(&expanded);
+    if options::get("xtrace") {
+        let ps4 = std::env::var("PS4").unwrap_or_else(|_| "+ ".to_string());
+        let shown: Vec<String> = args.iter().enumerate()
+            .map(|(i, a)| if raw.get(i).is_some_and(|typed| pipeline::is_operator(typed)) {
+                a.clone()
+            } else {
+                shlex::try_quote(a).map(|q| q.into_owned()).unwrap_or_else(|_| a.clone())
+            })
+            .collect();
+        eprintln!("{}{}", ps4, shown.join(" "));
+    }
+
+    conditions::quote_patterns(&mut args, &raw);
+    if pipeline::is_needed(&args, &raw) {
+        return pipeline::run(args, &raw);
+    }
+    match run_builtin(args.clone()) {
+        Some(status) => status,
+        // autocd: a lone directory name that is not a command means cd
+        None if args.len() == 1 && options::get("autocd")
+            && Path::new(&args[0]).is_dir() && find_executable(&args[0]).is_none() => {
+            small_utils::cd(vec!["cd".to_string(), args[0].clone()])
+        }
+        None => system_run(args),
+    }
+}
+
+/// Full path of a program as execvp would find it
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    let is_exec = |p: &Path| {
+        p.is_file() && p.metadata().is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+    };
+    if name.contains('/') {
+        let path = PathBuf::from(name);
+        return is_exec(&path).then_some(path);
+    }
+    env::var("PATH").ok()?
+        .split(':')
+        .map(|dir| Path::new(if dir.is_empty() { "." } else { dir }).join(name))
+        .find(|p| is_exec(p))
+}
+
+/// Runs a builtin command, None if args[0] is not a builtin
+fn run_builtin(args: Vec<String>) -> Option<i32> {
+    let status = match args[0].as_str() {
+        "print" => {
+            print::print(args)
+        }
+        "echo" => {
+            print::echo(args)
+        }
+        "printf" => {
+            print::printf(args)
+        }
+        "cd" => {
+            small_utils::cd(args)
+        }
+        "pushd" => {
+            dirs::handle_pushd(args)
+        }
+        "popd" => {
+            dirs::handle_popd(args)
+        }
+        "dirs" => {
+            dirs::handle_dirs(args)
+        }
+        "z" => {
+            frecency::handle_z(args)
+        }
+        "history" => {
+            history::handle_history(args)
+        }
+        "alias" => {
+            aliases::handle_alias(args)
+        }
+        "unalias" => {
+            aliases::handle_unalias(args)
+        }
+        "abbr" => {
+            aliases::handle_abbr(args)
+        }
+        "var" => {
+            var::handle_var(args);
+            0
+        }
+        "exit" => {
+            small_utils::exit(args);
+            0
+        }
+        "clr" => {
+            small_utils::clr();
+            0
+        }
+        "set" => {
+            options::handle_set(args)
+        }
+        "setopt" => {
+            options::handle_setopt(args, true)
+        }
+        "unsetopt" => {
+            options::handle_setopt(args, false)
+        }
+        "test" | "[" => {
+            conditions::test(args)
+        }
+        "[[" => {
+            conditions::extended_test(args)
+        }
+        "read" => {
+            read::handle_read(args)
+        }
+        "update" =>{
+            crate::GLOBAL_ENGINE.with(|eng| {
+            crate::GLOBAL_AST.with(|cell| {
+            if let Some(ast) = cell.borrow().as_ref() {
+                let engine = eng.borrow();
+                if let Err(e) = engine.run_ast(ast)
+                    && !e.to_string().contains("Function not found") {
+                    eprintln!("Error in config: {}", e);
                 }
-                });
-            });
-            }
-            _ => {
-                system_run(args);
             }
+            });
+        });
+            0
+        }
+        _ => return None,
+    };
+    Some(status)
+}
 
-        };
-        break;
+/// Converts a waitpid status into a shell exit status
+fn wait_status(status: i32) -> i32 {
+    if WIFEXITED(status) {
+        WEXITSTATUS(status)
+    } else if WIFSIGNALED(status) {
+        128 + WTERMSIG(status)
+    } else {
+        1
+    }
+}
+
+/// Body of a forked child: runs a builtin or replaces the process with the program
+fn exec_child(args: Vec<String>) -> ! {
+    // The shell ignores SIGPIPE; a child writing into a closed pipe should just stop
+    unsafe { libc::signal(libc::SIGPIPE, libc::SIG_DFL); }
+    if let Some(status) = run_builtin(args.clone()) {
+        let _ = std::io::stdout().flush();
+        unsafe { libc::_exit(status) }
     }
+
+    let c_args: Vec<CString> = args.iter()
+        .map(|arg| CString::new(arg.as_str()).expect("CString::new failed"))
+        .collect();
+
+    let mut argv_ptrs: Vec<*const libc::c_char> = c_args.iter()
+        .map(|arg| arg.as_ptr())
+        .collect();
+
+    argv_ptrs.push(ptr::null());
+    unsafe {
+        execvp(argv_ptrs[0], argv_ptrs.as_ptr());
+    }
+    eprintln!("sbsh: {}: command not found", args[0]);
+    unsafe { libc::_exit(127) }
 }
 
-fn system_run(args: Vec<String>) {
+fn system_run(args: Vec<String>) -> i32 {
+    let _ = std::io::stdout().flush();
     unsafe {
         let pid = fork();
         match pid {
             -1 => panic!("Fork Failed!"),
-            0 => {
-                let c_args: Vec<CString> = args.iter()
-                    .map(|arg| CString::new(arg.as_str()).expect("CString::new failed"))
-                    .collect();
-
-                let mut argv_ptrs: Vec<*const libc::c_char> = c_args.iter()
-                    .map(|arg| arg.as_ptr())
-                    .collect();
-
-                argv_ptrs.push(ptr::null());
-                execvp(argv_ptrs[0], argv_ptrs.as_ptr());
-                panic!("execvp failed!");
-            }
+            0 => exec_child(args),
             child_pid => {
                 let mut status: i32 = 0;
-                let ret = waitpid(child_pid, &mut status, 0);
+                let ret = timing::wait_child(child_pid, &mut status);
                 if ret == -1 {
                     eprintln!("waitpid error");
-                } else if WIFEXITED(status) {
-                    let code = WEXITSTATUS(status);
-                    if code != 0 {
-                        eprintln!("the program terminated with code {}", code);
-                    }
+                    return 1;
                 }
+                let code = wait_status(status);
+                if code != 0 {
+                    eprintln!("the program terminated with code {}", code);
+                }
+                code
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -1,34 +1,276 @@
 // Copyright (C) <2026> <Bogdan Yachmenv>
 // SPDX-License-Identifier: GPL-3.0/
 
+//Aliases (first word), global aliases (any word) and abbreviations (expanded in the editor)
 use std::collections::HashMap;
 use std::sync::Mutex;
 use lazy_static::lazy_static;
-
+use super::pipeline;
 
 lazy_static! {
     static ref ALIASES: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    static ref GLOBALS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    static ref ABBREVIATIONS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+}
+
+fn sorted(map: &HashMap<String, String>) -> Vec<(String, String)> {
+    let mut all: Vec<(String, String)> = map.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    all.sort();
+    all
 }
 
 pub fn add(name: &str, command: &str) {
+    GLOBALS.lock().unwrap().remove(name);
     ALIASES.lock().unwrap().insert(name.to_string(), command.to_string());
 }
 
+pub fn add_global(name: &str, command: &str) {
+    ALIASES.lock().unwrap().remove(name);
+    GLOBALS.lock().unwrap().insert(name.to_string(), command.to_string());
+}
+
 pub fn get(name: &str) -> Option<String> {
     ALIASES.lock().unwrap().get(name).cloned()
 }
 
-pub fn remove(name: &str) {
-    ALIASES.lock().unwrap().remove(name);
+pub fn get_global(name: &str) -> Option<String> {
+    GLOBALS.lock().unwrap().get(name).cloned()
 }
 
+/// Removes an alias of either kind, false if there was none
+pub fn remove(name: &str) -> bool {
+    let regular = ALIASES.lock().unwrap().remove(name).is_some();
+    GLOBALS.lock().unwrap().remove(name).is_some() || regular
+}
+
+/// All regular aliases sorted by name
 pub fn list() -> Vec<(String, String)> {
-    ALIASES.lock().unwrap()
-        .iter()
-        .map(|(k, v)| (k.clone(), v.clone()))
-        .collect()
+    sorted(&ALIASES.lock().unwrap())
+}
+
+/// All global aliases sorted by name
+pub fn list_global() -> Vec<(String, String)> {
+    sorted(&GLOBALS.lock().unwrap())
 }
 
 pub fn clear() {
     ALIASES.lock().unwrap().clear();
+    GLOBALS.lock().unwrap().clear();
+}
+
+pub fn add_abbreviation(name: &str, expansion: &str) {
+    ABBREVIATIONS.lock().unwrap().insert(name.to_string(), expansion.to_string());
+}
+
+pub fn abbreviation(name: &str) -> Option<String> {
+    ABBREVIATIONS.lock().unwrap().get(name).cloned()
+}
+
+pub fn remove_abbreviation(name: &str) -> bool {
+    ABBREVIATIONS.lock().unwrap().remove(name).is_some()
+}
+
+/// All abbreviations sorted by name
+pub fn list_abbreviations() -> Vec<(String, String)> {
+    sorted(&ABBREVIATIONS.lock().unwrap())
+}
+
+/// Command line for alias `command` run with `args` (the words after its name as typed,
+/// quotes kept). $1..$9 and $@ take the arguments of the first command; without
+/// placeholders they are appended. Everything from the first operator on is kept as it is
+pub fn expand(command: &str, args: &[String]) -> String {
+    let split = args.iter().position(|arg| pipeline::is_operator(arg)).unwrap_or(args.len());
+    let (own, tail) = args.split_at(split);
+
+    let mut line = String::new();
+    let mut placeholders = false;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek().copied()) {
+            ('$', Some('@')) => {
+                chars.next();
+                line.push_str(&own.join(" "));
+                placeholders = true;
+            }
+            ('$', Some(digit @ '1'..='9')) => {
+                chars.next();
+                let n = digit as usize - '1' as usize;
+                line.push_str(own.get(n).map(String::as_str).unwrap_or_default());
+                placeholders = true;
+            }
+            _ => line.push(c),
+        }
+    }
+    let rest = if placeholders { &[] } else { own };
+    for arg in rest.iter().chain(tail) {
+        line.push(' ');
+        line.push_str(arg);
+    }
+    line
+}
+
+/// Replaces unquoted words that are global aliases, anywhere on the line
+pub fn expand_globals(line: &str) -> String {
+    let globals = GLOBALS.lock().unwrap();
+    // alias and unalias get the names themselves
+    let first = line.split_whitespace().next().unwrap_or_default();
+    if globals.is_empty() || first == "alias" || first == "unalias" {
+        return line.to_string();
+    }
+    let mut out = String::with_capacity(line.len());
+    let mut word = String::new();
+    let mut quote: Option<char> = None;
+    let mut quoted = false;
+    let flush = |word: &mut String, quoted: bool, out: &mut String| {
+        match globals.get(word.as_str()) {
+            Some(command) if !quoted => out.push_str(command),
+            _ => out.push_str(word),
+        }
+        word.clear();
+    };
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if quote != Some('\'') => {
+                word.push(c);
+                word.extend(chars.next());
+                quoted = true;
+            }
+            '\'' | '"' if quote.is_none() => {
+                quote = Some(c);
+                quoted = true;
+                word.push(c);
+            }
+            c if Some(c) == quote => {
+                quote = None;
+                word.push(c);
+            }
+            c if c.is_whitespace() && quote.is_none() => {
+                flush(&mut word, quoted, &mut out);
+                quoted = false;
+                out.push(c);
+            }
+            _ => word.push(c),
+        }
+    }
+    flush(&mut word, quoted, &mut out);
+    out
+}
+
+/// The abbreviation ending at `pos`, if its word is in command position
+/// (start of the line or after a `|` word, as pipelines split): start of the word and its expansion
+pub fn abbreviation_at(line: &str, pos: usize) -> Option<(usize, String)> {
+    let before = line.get(..pos)?;
+    let start = before.char_indices().rev()
+        .find(|&(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let expansion = abbreviation(&before[start..])?;
+    let previous = before[..start].split_whitespace().last();
+    (previous.is_none() || previous == Some("|")).then_some((start, expansion))
+}
+
+/// Expands the abbreviation at the end of a submitted line
+pub fn expand_abbreviation(line: &str) -> String {
+    match abbreviation_at(line, line.len()) {
+        Some((start, expansion)) => format!("{}{}", &line[..start], expansion),
+        None => line.to_string(),
+    }
+}
+
+/// How `alias` prints an alias so that the output can be run again
+fn display(name: &str, command: &str, global: bool) -> String {
+    let quoted = shlex::try_quote(command).map(|q| q.into_owned()).unwrap_or_else(|_| command.to_string());
+    format!("alias {}{}={}", if global { "-g " } else { "" }, name, quoted)
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(|c: char| c.is_whitespace() || "=/'\"$|&;<>".contains(c))
+}
+
+/// Alias builtin: alias [-p] [-g] [name[=command] ...]
+pub fn handle_alias(args: Vec<String>) -> i32 {
+    let global = args.iter().skip(1).any(|arg| arg == "-g");
+    let names: Vec<&String> = args.iter().skip(1).filter(|arg| *arg != "-p" && *arg != "-g").collect();
+    if names.is_empty() {
+        if !global {
+            for (name, command) in list() {
+                println!("{}", display(&name, &command, false));
+            }
+        }
+        for (name, command) in list_global() {
+            println!("{}", display(&name, &command, true));
+        }
+        return 0;
+    }
+
+    let mut status = 0;
+    for arg in names {
+        match arg.split_once('=') {
+            Some((name, command)) if is_valid_name(name) && global => add_global(name, command),
+            Some((name, command)) if is_valid_name(name) => add(name, command),
+            Some((name, _)) => {
+                eprintln!("alias: {}: invalid alias name", name);
+                status = 1;
+            }
+            None => match (get(arg), get_global(arg)) {
+                (Some(command), _) => println!("{}", display(arg, &command, false)),
+                (None, Some(command)) => println!("{}", display(arg, &command, true)),
+                (None, None) => {
+                    eprintln!("alias: {}: not found", arg);
+                    status = 1;
+                }
+            },
+        }
+    }
+    status
+}
+
+/// Unalias builtin: unalias -a | name ...
+pub fn handle_unalias(args: Vec<String>) -> i32 {
+    if args.len() < 2 {
+        eprintln!("unalias: usage: unalias [-a] name [name ...]");
+        return 2;
+    }
+    let mut status = 0;
+    for arg in args.iter().skip(1) {
+        if arg == "-a" {
+            clear();
+        } else if !remove(arg) {
+            eprintln!("unalias: {}: not found", arg);
+            status = 1;
+        }
+    }
+    status
+}
+
+/// Abbr builtin: abbr [name=expansion ...] | abbr -e name ...
+pub fn handle_abbr(args: Vec<String>) -> i32 {
+    if args.len() < 2 {
+        for (name, expansion) in list_abbreviations() {
+            let quoted = shlex::try_quote(&expansion).map(|q| q.into_owned()).unwrap_or_else(|_| expansion.clone());
+            println!("abbr {}={}", name, quoted);
+        }
+        return 0;
+    }
+
+    let mut status = 0;
+    if args[1] == "-e" {
+        for name in args.iter().skip(2) {
+            if !remove_abbreviation(name) {
+                eprintln!("abbr: {}: not found", name);
+                status = 1;
+            }
+        }
+        return status;
+    }
+    for arg in args.iter().skip(1) {
+        match arg.split_once('=') {
+            Some((name, expansion)) if is_valid_name(name) => add_abbreviation(name, expansion),
+            _ => {
+                eprintln!("abbr: {}: expected name=expansion", arg);
+                status = 2;
+            }
+        }
+    }
+    status
 }
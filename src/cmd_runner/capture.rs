@@ -0,0 +1,216 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Runs a command line in a child process and captures its output (run_command in Rhai)
+use std::env;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use libc::{c_int, close, dup2, fork, pipe};
+use super::{dispatch, timing, wait_status};
+
+/// Exit status when the timeout kills the command, as with timeout(1)
+pub const TIMEOUT_STATUS: i32 = 124;
+
+#[derive(Default)]
+pub struct Options {
+    //Text given on stdin, stdin is empty otherwise
+    pub stdin: Option<String>,
+    pub env: Vec<(String, String)>,
+    pub cwd: Option<String>,
+    pub timeout: Option<Duration>,
+}
+
+pub struct Output {
+    pub out: String,
+    pub err: String,
+    pub code: i32,
+    pub duration: Duration,
+}
+
+fn new_pipe() -> Result<[c_int; 2], String> {
+    let mut fds: [c_int; 2] = [-1, -1];
+    if unsafe { pipe(fds.as_mut_ptr()) } == -1 {
+        return Err("pipe failed".to_string());
+    }
+    Ok(fds)
+}
+
+/// Body of the child: stdin/stdout/stderr already point to the pipes
+fn child(line: &str, options: &Options) -> ! {
+    if let Some(cwd) = &options.cwd
+        && let Err(e) = env::set_current_dir(cwd) {
+        eprintln!("sbsh: {}: {}", cwd, e);
+        unsafe { libc::_exit(1) }
+    }
+    for (key, value) in &options.env {
+        super::var::set(key, value);
+    }
+    let status = dispatch(line);
+    let _ = std::io::stdout().flush();
+    unsafe { libc::_exit(status) }
+}
+
+/// Feeds stdin and reads stdout and stderr until the child closes them or the deadline passes,
+/// then closes the pipes. Returns false on timeout
+fn exchange(fds: [c_int; 3], input: &[u8], out: &mut Vec<u8>, err: &mut Vec<u8>, deadline: Option<Instant>) -> bool {
+    // -1 marks a closed pipe
+    let [mut stdin, stdout, stderr] = fds;
+    let mut open = [stdout, stderr];
+    let mut written = 0;
+    if input.is_empty() {
+        unsafe { close(stdin); }
+        stdin = -1;
+    } else {
+        // Partial writes, so a command busy writing its output never blocks the shell
+        unsafe { libc::fcntl(stdin, libc::F_SETFL, libc::fcntl(stdin, libc::F_GETFL) | libc::O_NONBLOCK); }
+    }
+
+    let finished = loop {
+        if open.iter().all(|fd| *fd == -1) {
+            break true;
+        }
+        let mut polled: Vec<libc::pollfd> = open.iter()
+            .filter(|fd| **fd != -1)
+            .map(|fd| libc::pollfd { fd: *fd, events: libc::POLLIN, revents: 0 })
+            .collect();
+        if stdin != -1 {
+            polled.push(libc::pollfd { fd: stdin, events: libc::POLLOUT, revents: 0 });
+        }
+        let wait = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(left) => left.as_millis().min(c_int::MAX as u128) as c_int,
+                None => break false,
+            },
+            None => -1,
+        };
+        let ready = unsafe { libc::poll(polled.as_mut_ptr(), polled.len() as libc::nfds_t, wait) };
+        if ready == -1 {
+            if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            break true;
+        }
+        if ready == 0 {
+            break false;
+        }
+
+        for p in polled.iter().filter(|p| p.revents != 0) {
+            if p.fd == stdin {
+                let chunk = &input[written..];
+                let n = unsafe { libc::write(stdin, chunk.as_ptr() as *const libc::c_void, chunk.len()) };
+                if n > 0 {
+                    written += n as usize;
+                } else if std::io::Error::last_os_error().kind() == std::io::ErrorKind::WouldBlock {
+                    continue;
+                }
+                // Done, or the command does not read its input
+                if n <= 0 || written == input.len() {
+                    unsafe { close(stdin); }
+                    stdin = -1;
+                }
+                continue;
+            }
+            let mut buf = [0u8; 8192];
+            let n = unsafe { libc::read(p.fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+            if n > 0 {
+                let target = if p.fd == stdout { &mut *out } else { &mut *err };
+                target.extend_from_slice(&buf[..n as usize]);
+            } else {
+                unsafe { close(p.fd); }
+                for fd in open.iter_mut().filter(|fd| **fd == p.fd) {
+                    *fd = -1;
+                }
+            }
+        }
+    };
+
+    for fd in open.into_iter().chain([stdin]).filter(|fd| *fd != -1) {
+        unsafe { close(fd); }
+    }
+    finished
+}
+
+/// Command started by start(): its pid and the shell's ends of its pipes
+pub struct Child {
+    pid: libc::pid_t,
+    fds: [c_int; 3],
+    started: Instant,
+}
+
+/// Starts `line` like a line typed at the prompt, but in a child process with its output captured
+pub fn start(line: &str, options: &Options) -> Result<Child, String> {
+    let started = Instant::now();
+    let stdin = new_pipe()?;
+    let stdout = new_pipe()?;
+    let stderr = new_pipe()?;
+    let _ = std::io::stdout().flush();
+
+    let pid = unsafe { fork() };
+    if pid == -1 {
+        return Err("fork failed".to_string());
+    }
+    if pid == 0 {
+        unsafe {
+            libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+            // With a timeout: own process group, so killing it also stops the programs it started
+            if options.timeout.is_some() {
+                libc::setpgid(0, 0);
+            }
+            dup2(stdin[0], 0);
+            dup2(stdout[1], 1);
+            dup2(stderr[1], 2);
+            for fd in stdin.iter().chain(&stdout).chain(&stderr) {
+                close(*fd);
+            }
+        }
+        child(line, options);
+    }
+
+    unsafe {
+        // Also here: the timeout may kill the group before the child has got to setpgid
+        if options.timeout.is_some() {
+            libc::setpgid(pid, pid);
+        }
+        close(stdin[0]);
+        close(stdout[1]);
+        close(stderr[1]);
+    }
+    Ok(Child { pid, fds: [stdin[1], stdout[0], stderr[0]], started })
+}
+
+/// Feeds the input to a started command and waits for it; may run on another thread
+pub fn finish(child: Child, options: &Options) -> Output {
+    let Child { pid, fds, started } = child;
+    let input = options.stdin.clone().unwrap_or_default();
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    let deadline = options.timeout.map(|timeout| started + timeout);
+    let finished = exchange(fds, input.as_bytes(), &mut out, &mut err, deadline);
+    if !finished {
+        unsafe {
+            if libc::kill(-pid, libc::SIGKILL) == -1 {
+                libc::kill(pid, libc::SIGKILL);
+            }
+        }
+    }
+
+    let mut status: c_int = 0;
+    let code = if timing::wait_child(pid, &mut status) == -1 {
+        1
+    } else if !finished {
+        TIMEOUT_STATUS
+    } else {
+        wait_status(status)
+    };
+    Output {
+        out: String::from_utf8_lossy(&out).into_owned(),
+        err: String::from_utf8_lossy(&err).into_owned(),
+        code,
+        duration: started.elapsed(),
+    }
+}
+
+/// Runs `line` in a child process and returns its captured output
+pub fn run(line: &str, options: &Options) -> Result<Output, String> {
+    let child = start(line, options)?;
+    Ok(finish(child, options))
+}
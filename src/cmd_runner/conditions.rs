@@ -0,0 +1,337 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Conditional builtins: test, [ and [[ ]]
+use std::ffi::CString;
+use std::fs;
+use std::time::SystemTime;
+use regex::Regex;
+use crate::api;
+
+/// Test builtin: `test expr` or `[ expr ]`, status 0 if true, 1 if false, 2 on error
+pub fn test(args: Vec<String>) -> i32 {
+    let mut tokens = &args[1..];
+    if args[0] == "[" {
+        match tokens.split_last() {
+            Some((last, rest)) if last == "]" => tokens = rest,
+            _ => {
+                eprintln!("[: missing ']'");
+                return 2;
+            }
+        }
+    }
+    evaluate(&args[0], tokens, false)
+}
+
+/// In `[[ ]]` the right side of =, == and != is a pattern only where it is not quoted:
+/// escapes the quoted parts of those words so that they compare literally.
+/// `raw` are the words as typed (see pipeline::raw_words)
+pub fn quote_patterns(args: &mut [String], raw: &[String]) {
+    if args.first().is_none_or(|first| first != "[[") || raw.len() != args.len() {
+        return;
+    }
+    for i in 2..args.len() {
+        if matches!(args[i - 1].as_str(), "=" | "==" | "!=") && raw[i] != args[i] {
+            args[i] = literal_pattern(&raw[i]);
+        }
+    }
+}
+
+/// Removes the quotes of a word, escaping the glob characters that were inside them
+fn literal_pattern(raw: &str) -> String {
+    let mut out = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = raw.chars().peekable();
+    let escaped = |out: &mut String, c: char| {
+        if "*?[]\\".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    };
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '\\') => {
+                out.push(c);
+                out.extend(chars.next());
+            }
+            (None, _) => out.push(c),
+            (Some(q), _) if c == q => quote = None,
+            (Some('"'), '\\') if chars.peek().is_some_and(|next| "$`\"\\".contains(*next)) => {
+                let next = chars.next().unwrap();
+                escaped(&mut out, next);
+            }
+            (Some(_), _) => escaped(&mut out, c),
+        }
+    }
+    out
+}
+
+/// Extended test: `[[ expr ]]` with &&, ||, glob patterns and =~ regex matching
+pub fn extended_test(args: Vec<String>) -> i32 {
+    match args[1..].split_last() {
+        Some((last, rest)) if last == "]]" => evaluate("[[", rest, true),
+        _ => {
+            eprintln!("[[: missing ']]'");
+            2
+        }
+    }
+}
+
+fn evaluate(name: &str, tokens: &[String], extended: bool) -> i32 {
+    if tokens.is_empty() {
+        return 1;
+    }
+    let mut parser = Parser { tokens, pos: 0, extended };
+    let result = parser.or().and_then(|value| {
+        if parser.pos < tokens.len() {
+            Err(format!("unexpected argument '{}'", tokens[parser.pos]))
+        } else {
+            Ok(value)
+        }
+    });
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            eprintln!("{}: {}", name, e);
+            2
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+    extended: bool,
+}
+
+impl Parser<'_> {
+    fn peek(&self, offset: usize) -> Option<&str> {
+        self.tokens.get(self.pos + offset).map(|s| s.as_str())
+    }
+
+    fn next(&mut self) -> Result<&str, String> {
+        let token = self.tokens.get(self.pos).ok_or("argument expected")?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn or(&mut self) -> Result<bool, String> {
+        let op = if self.extended { "||" } else { "-o" };
+        let mut value = self.and()?;
+        while self.peek(0) == Some(op) {
+            self.pos += 1;
+            let right = self.and()?;
+            value = value || right;
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<bool, String> {
+        let op = if self.extended { "&&" } else { "-a" };
+        let mut value = self.not()?;
+        while self.peek(0) == Some(op) {
+            self.pos += 1;
+            let right = self.not()?;
+            value = value && right;
+        }
+        Ok(value)
+    }
+
+    fn not(&mut self) -> Result<bool, String> {
+        if self.peek(0) == Some("!") && self.peek(1).is_some() {
+            self.pos += 1;
+            return Ok(!self.not()?);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<bool, String> {
+        if self.peek(0) == Some("(") {
+            self.pos += 1;
+            let value = self.or()?;
+            if self.next()? != ")" {
+                return Err("missing ')'".to_string());
+            }
+            return Ok(value);
+        }
+
+        if let (Some(op), Some(_)) = (self.peek(1), self.peek(2))
+            && is_binary(op, self.extended) {
+            let left = self.next()?.to_string();
+            let op = self.next()?.to_string();
+            let right = self.next()?.to_string();
+            return binary(&left, &op, &right, self.extended);
+        }
+
+        if let (Some(op), Some(_)) = (self.peek(0), self.peek(1))
+            && is_unary(op) {
+            let op = self.next()?.to_string();
+            let operand = self.next()?.to_string();
+            return Ok(unary(&op, &operand));
+        }
+
+        // A single word is true if it is not empty
+        Ok(!self.next()?.is_empty())
+    }
+}
+
+fn is_unary(op: &str) -> bool {
+    matches!(op, "-e" | "-f" | "-d" | "-x" | "-r" | "-w" | "-s" | "-L" | "-h" | "-n" | "-z")
+}
+
+fn is_binary(op: &str, extended: bool) -> bool {
+    match op {
+        "=" | "==" | "!=" | "-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge" | "-nt" | "-ot" => true,
+        "=~" | "<" | ">" => extended,
+        _ => false,
+    }
+}
+
+fn access(path: &str, mode: libc::c_int) -> bool {
+    match CString::new(path) {
+        Ok(c_path) => unsafe { libc::access(c_path.as_ptr(), mode) == 0 },
+        Err(_) => false,
+    }
+}
+
+fn unary(op: &str, operand: &str) -> bool {
+    match op {
+        "-e" => fs::metadata(operand).is_ok(),
+        "-f" => api::is_file(operand),
+        "-d" => api::is_dir(operand),
+        "-x" => access(operand, libc::X_OK),
+        "-r" => access(operand, libc::R_OK),
+        "-w" => access(operand, libc::W_OK),
+        "-s" => fs::metadata(operand).map(|m| m.len() > 0).unwrap_or(false),
+        "-L" | "-h" => fs::symlink_metadata(operand).map(|m| m.file_type().is_symlink()).unwrap_or(false),
+        "-n" => !operand.is_empty(),
+        "-z" => operand.is_empty(),
+        _ => false,
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn integer(value: &str) -> Result<i64, String> {
+    value.trim().parse().map_err(|_| format!("{}: integer expression expected", value))
+}
+
+fn binary(left: &str, op: &str, right: &str, extended: bool) -> Result<bool, String> {
+    Ok(match op {
+        "=" | "==" if extended => glob_match(right, left),
+        "!=" if extended => !glob_match(right, left),
+        "=" | "==" => left == right,
+        "!=" => left != right,
+        "<" => left < right,
+        ">" => left > right,
+        "=~" => Regex::new(right)
+            .map_err(|e| format!("invalid regex '{}': {}", right, e))?
+            .is_match(left),
+        "-eq" => integer(left)? == integer(right)?,
+        "-ne" => integer(left)? != integer(right)?,
+        "-lt" => integer(left)? < integer(right)?,
+        "-le" => integer(left)? <= integer(right)?,
+        "-gt" => integer(left)? > integer(right)?,
+        "-ge" => integer(left)? >= integer(right)?,
+        // A missing file is older than any existing one
+        "-nt" => match (modified(left), modified(right)) {
+            (Some(l), Some(r)) => l > r,
+            (Some(_), None) => true,
+            _ => false,
+        },
+        "-ot" => match (modified(left), modified(right)) {
+            (Some(l), Some(r)) => l < r,
+            (None, Some(_)) => true,
+            _ => false,
+        },
+        _ => return Err(format!("{}: unknown operator", op)),
+    })
+}
+
+/// Shell glob matching: `*`, `?`, `[abc]`, `[a-z]`, `[!abc]` and `\` escapes
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_at(&pattern, &text)
+}
+
+/// Iterative matching with backtracking to the last `*` only, linear in practice
+fn glob_at(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Position after the last '*' and the text position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if pattern.get(p) == Some(&'*') {
+            p += 1;
+            star = Some((p, t));
+            continue;
+        }
+        if let Some(next) = match_one(pattern, p, text[t]) {
+            p = next;
+            t += 1;
+        } else if let Some((after_star, tried)) = star {
+            // Let the '*' take one more character
+            p = after_star;
+            t = tried + 1;
+            star = Some((after_star, t));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Matches `c` against the pattern element at `p` (not '*'), returns the position after it
+fn match_one(pattern: &[char], p: usize, c: char) -> Option<usize> {
+    let matched = match *pattern.get(p)? {
+        '?' => return Some(p + 1),
+        '[' => match class_end(&pattern[p..]) {
+            Some(end) => return class_matches(&pattern[p + 1..p + end], c).then_some(p + end + 1),
+            // An unclosed '[' is an ordinary character
+            None => c == '[',
+        },
+        '\\' if p + 1 < pattern.len() => return (pattern[p + 1] == c).then_some(p + 2),
+        other => other == c,
+    };
+    matched.then_some(p + 1)
+}
+
+///Index of the ']' closing a character class that starts at pattern[0]
+fn class_end(pattern: &[char]) -> Option<usize> {
+    let mut i = 1;
+    if matches!(pattern.get(i), Some('!') | Some('^')) {
+        i += 1;
+    }
+    // ']' right after the opening bracket is a literal
+    if pattern.get(i) == Some(&']') {
+        i += 1;
+    }
+    pattern[i..].iter().position(|c| *c == ']').map(|pos| pos + i)
+}
+
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negate, class) = match class.first() {
+        Some('!') | Some('^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            if class[i] <= c && c <= class[i + 2] {
+                found = true;
+            }
+            i += 3;
+        } else {
+            if class[i] == c {
+                found = true;
+            }
+            i += 1;
+        }
+    }
+    found != negate
+}
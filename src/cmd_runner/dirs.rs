@@ -0,0 +1,211 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Directory stack: pushd, popd, dirs and ~N references
+use std::env;
+use std::ffi::{CStr, CString};
+use std::sync::Mutex;
+use lazy_static::lazy_static;
+use super::small_utils::change_dir;
+
+lazy_static! {
+    //Saved directories, the current directory is not stored (it is entry 0)
+    static ref STACK: Mutex<Vec<String>> = Mutex::new(Vec::new());
+}
+
+fn current() -> String {
+    env::current_dir()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// The whole stack as `dirs` shows it: current directory first
+fn entries() -> Vec<String> {
+    let mut all = vec![current()];
+    all.extend(STACK.lock().unwrap().iter().cloned());
+    all
+}
+
+/// Index into entries() from "+N" (from the left) or "-N" (from the right)
+fn parse_index(arg: &str, len: usize) -> Option<usize> {
+    let (from_right, digits) = match arg.as_bytes().first() {
+        Some(b'+') => (false, &arg[1..]),
+        Some(b'-') => (true, &arg[1..]),
+        _ => return None,
+    };
+    let n: usize = digits.parse().ok()?;
+    if n >= len {
+        return None;
+    }
+    Some(if from_right { len - 1 - n } else { n })
+}
+
+fn is_index(arg: &str) -> bool {
+    arg.len() > 1 && (arg.starts_with('+') || arg.starts_with('-')) && arg[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+/// Entry N of the stack for ~N, ~+N and ~-N
+pub fn entry(arg: &str) -> Option<String> {
+    let all = entries();
+    let arg = if arg.starts_with('+') || arg.starts_with('-') { arg.to_string() } else { format!("+{}", arg) };
+    parse_index(&arg, all.len()).map(|i| all[i].clone())
+}
+
+fn home_dir_of(user: &str) -> Option<String> {
+    let name = CString::new(user).ok()?;
+    unsafe {
+        let pw = libc::getpwnam(name.as_ptr());
+        if pw.is_null() {
+            return None;
+        }
+        Some(CStr::from_ptr((*pw).pw_dir).to_string_lossy().into_owned())
+    }
+}
+
+/// Resolves the part of a word between `~` and the first `/`:
+/// ~, ~+, ~-, ~N, ~+N, ~-N and ~user
+pub fn expand_tilde(prefix: &str) -> Option<String> {
+    let rest = prefix.strip_prefix('~')?;
+    match rest {
+        "" => env::var("HOME").ok(),
+        "+" => env::var("PWD").ok().or_else(|| Some(current())),
+        "-" => env::var("OLDPWD").ok(),
+        _ if rest.trim_start_matches(['+', '-']).chars().all(|c| c.is_ascii_digit()) => entry(rest),
+        _ => home_dir_of(rest),
+    }
+}
+
+fn display(path: &str, long: bool) -> String {
+    if !long
+        && let Ok(home) = env::var("HOME")
+        && !home.is_empty()
+        && (path == home || path.starts_with(&format!("{}/", home))) {
+        return format!("~{}", &path[home.len()..]);
+    }
+    path.to_string()
+}
+
+fn print_stack(long: bool, per_line: bool, numbered: bool) {
+    let all = entries();
+    if numbered {
+        for (i, dir) in all.iter().enumerate() {
+            println!("{:>2}  {}", i, display(dir, long));
+        }
+    } else if per_line {
+        for dir in &all {
+            println!("{}", display(dir, long));
+        }
+    } else {
+        let shown: Vec<String> = all.iter().map(|d| display(d, long)).collect();
+        println!("{}", shown.join(" "));
+    }
+}
+
+/// Dirs builtin: dirs [-c] [-l] [-p] [-v] [+N | -N]
+pub fn handle_dirs(args: Vec<String>) -> i32 {
+    let (mut long, mut per_line, mut numbered) = (false, false, false);
+    for arg in args.iter().skip(1) {
+        if is_index(arg) {
+            let all = entries();
+            return match parse_index(arg, all.len()) {
+                Some(i) => {
+                    println!("{}", display(&all[i], long));
+                    0
+                }
+                None => {
+                    eprintln!("dirs: {}: directory stack index out of range", arg);
+                    1
+                }
+            };
+        }
+        match arg.as_str() {
+            "-c" => {
+                STACK.lock().unwrap().clear();
+                return 0;
+            }
+            "-l" => long = true,
+            "-p" => per_line = true,
+            "-v" => numbered = true,
+            _ => {
+                eprintln!("dirs: {}: invalid option", arg);
+                return 2;
+            }
+        }
+    }
+    print_stack(long, per_line, numbered);
+    0
+}
+
+/// Pushd builtin: pushd [dir | +N | -N]
+pub fn handle_pushd(args: Vec<String>) -> i32 {
+    let old = current();
+    match args.get(1) {
+        // Swap the two top directories
+        None => {
+            let Some(top) = STACK.lock().unwrap().first().cloned() else {
+                eprintln!("pushd: no other directory");
+                return 1;
+            };
+            if let Err(e) = change_dir(&top) {
+                eprintln!("pushd: {}", e);
+                return 1;
+            }
+            STACK.lock().unwrap()[0] = old;
+        }
+        // Rotate so entry N becomes the current directory
+        Some(arg) if is_index(arg) => {
+            let mut all = entries();
+            let Some(n) = parse_index(arg, all.len()) else {
+                eprintln!("pushd: {}: directory stack index out of range", arg);
+                return 1;
+            };
+            all.rotate_left(n);
+            if let Err(e) = change_dir(&all[0]) {
+                eprintln!("pushd: {}", e);
+                return 1;
+            }
+            *STACK.lock().unwrap() = all[1..].to_vec();
+        }
+        Some(dir) => {
+            if let Err(e) = change_dir(dir) {
+                eprintln!("pushd: {}", e);
+                return 1;
+            }
+            STACK.lock().unwrap().insert(0, old);
+        }
+    }
+    print_stack(false, false, false);
+    0
+}
+
+/// Popd builtin: popd [+N | -N]
+pub fn handle_popd(args: Vec<String>) -> i32 {
+    let len = entries().len();
+    if len < 2 {
+        eprintln!("popd: directory stack empty");
+        return 1;
+    }
+    let n = match args.get(1) {
+        None => 0,
+        Some(arg) => match parse_index(arg, len) {
+            Some(n) => n,
+            None => {
+                eprintln!("popd: {}: directory stack index out of range", arg);
+                return 1;
+            }
+        },
+    };
+
+    if n == 0 {
+        let top = STACK.lock().unwrap()[0].clone();
+        if let Err(e) = change_dir(&top) {
+            eprintln!("popd: {}", e);
+            return 1;
+        }
+        STACK.lock().unwrap().remove(0);
+    } else {
+        STACK.lock().unwrap().remove(n - 1);
+    }
+    print_stack(false, false, false);
+    0
+}
@@ -0,0 +1,159 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Frecency database of visited directories and the z builtin
+//File format: one "path<TAB>rank<TAB>last visit (unix seconds)" per line
+use std::env;
+use std::fs;
+use std::path::Path;
+use chrono::Utc;
+use super::small_utils::change_dir;
+
+/// When the sum of all ranks grows past this, every rank is aged
+const MAX_TOTAL_RANK: f64 = 9000.0;
+
+struct Entry {
+    path: String,
+    rank: f64,
+    time: i64,
+}
+
+fn db_path() -> Option<String> {
+    env::var("HOME").ok().map(|home| home + "/.sbsh_dirs")
+}
+
+fn load() -> Vec<Entry> {
+    let Some(path) = db_path() else {
+        return Vec::new();
+    };
+    let content = fs::read_to_string(path).unwrap_or_default();
+    content.lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\t');
+            let path = parts.next()?.to_string();
+            let rank = parts.next()?.parse().ok()?;
+            let time = parts.next()?.parse().ok()?;
+            Some(Entry { path, rank, time })
+        })
+        .collect()
+}
+
+fn save(entries: &[Entry]) {
+    let Some(path) = db_path() else {
+        return;
+    };
+    let content: String = entries.iter()
+        .map(|e| format!("{}\t{}\t{}\n", e.path, e.rank, e.time))
+        .collect();
+    // Write a temporary file and rename it so a crash never leaves half a database
+    let tmp = format!("{}.{}", path, std::process::id());
+    if fs::write(&tmp, content).is_ok() {
+        let _ = fs::rename(&tmp, &path);
+    }
+}
+
+/// Records a visit to `dir`, called by change_dir
+pub fn add(dir: &str) {
+    let home = env::var("HOME").unwrap_or_default();
+    if dir == home || dir == "/" || dir.contains('\t') || dir.contains('\n') {
+        return;
+    }
+    let now = Utc::now().timestamp();
+    let mut entries = load();
+
+    match entries.iter_mut().find(|e| e.path == dir) {
+        Some(entry) => {
+            entry.rank += 1.0;
+            entry.time = now;
+        }
+        None => entries.push(Entry { path: dir.to_string(), rank: 1.0, time: now }),
+    }
+
+    if entries.iter().map(|e| e.rank).sum::<f64>() > MAX_TOTAL_RANK {
+        for entry in entries.iter_mut() {
+            entry.rank *= 0.99;
+        }
+        entries.retain(|e| e.rank >= 1.0);
+    }
+    save(&entries);
+}
+
+/// Rank weighted by how recently the directory was visited
+fn score(entry: &Entry, now: i64) -> f64 {
+    let age = now - entry.time;
+    if age < 3600 {
+        entry.rank * 4.0
+    } else if age < 86400 {
+        entry.rank * 2.0
+    } else if age < 604800 {
+        entry.rank / 2.0
+    } else {
+        entry.rank / 4.0
+    }
+}
+
+/// Lowercase with the same byte offsets as `text`: characters whose lowercase form
+/// has a different length are kept as they are
+fn fold_case(text: &str) -> String {
+    text.chars().map(|c| {
+        let lower: String = c.to_lowercase().collect();
+        if lower.len() == c.len_utf8() { lower } else { c.to_string() }
+    }).collect()
+}
+
+/// True if every term appears in `path` in order; case-insensitive unless a term has capitals
+fn matches(path: &str, terms: &[String]) -> bool {
+    let folded = fold_case(path);
+    let mut pos = 0;
+    for term in terms {
+        let (haystack, needle) = if term.chars().any(|c| c.is_uppercase()) {
+            (path, term.clone())
+        } else {
+            (folded.as_str(), fold_case(term))
+        };
+        match haystack[pos..].find(&needle) {
+            Some(found) => pos += found + needle.len(),
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Existing directories matching all terms, best first, with their scores
+pub fn query(terms: &[String]) -> Vec<(String, f64)> {
+    let now = Utc::now().timestamp();
+    let mut found: Vec<(String, f64)> = load().iter()
+        .filter(|e| matches(&e.path, terms) && Path::new(&e.path).is_dir())
+        .map(|e| (e.path.clone(), score(e, now)))
+        .collect();
+    found.sort_by(|a, b| b.1.total_cmp(&a.1));
+    found
+}
+
+/// Z builtin: z [-l] [fragment...]
+pub fn handle_z(args: Vec<String>) -> i32 {
+    let list = args.get(1).is_some_and(|a| a == "-l");
+    let terms: Vec<String> = args.iter().skip(if list { 2 } else { 1 }).cloned().collect();
+    let found = query(&terms);
+
+    if list || terms.is_empty() {
+        for (path, score) in found.iter().rev() {
+            println!("{:<10.1} {}", score, path);
+        }
+        return 0;
+    }
+
+    match found.first() {
+        Some((path, _)) => match change_dir(path) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("z: {}", e);
+                1
+            }
+        },
+        None => {
+            eprintln!("z: no match for '{}'", terms.join(" "));
+            1
+        }
+    }
+}
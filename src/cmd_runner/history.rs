@@ -0,0 +1,570 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//History database and the history builtin
+//File format: one "time<TAB>session<TAB>cwd<TAB>duration<TAB>status<TAB>command" per line,
+//time in unix seconds, duration in seconds; tabs, newlines and backslashes are escaped
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use super::{conditions, options};
+
+/// Entries kept when HISTSIZE is not set
+const DEFAULT_SIZE: usize = 10000;
+
+/// Secrets removed from saved commands; only the first group is replaced if there is one
+const DEFAULT_SECRETS: &[&str] = &[
+    r"(?i)(?:password|passwd|token|secret|api_?key)=(\S+)",
+    r"(?i)--password[= ](\S+)",
+    r"(?i)authorization:\s*bearer\s+([^\s'\x22]+)",
+    r"://[^/\s:@]+:([^/\s@]+)@",
+    r"\b(gh[pousr]_[A-Za-z0-9]{36,})",
+    r"\b(AKIA[0-9A-Z]{16})",
+    r"\b(sk-[A-Za-z0-9_-]{20,})",
+];
+
+lazy_static! {
+    //Identifies the commands of this shell among those of other shells sharing the file
+    static ref SESSION: String = format!("{}-{}", Utc::now().timestamp(), std::process::id());
+    //Entries loaded at startup plus the ones added since, used for suggestions
+    static ref ENTRIES: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+    //Entry of the command being run
+    static ref RUNNING: Mutex<Option<Running>> = Mutex::new(None);
+    static ref SECRETS: Mutex<Vec<Regex>> = Mutex::new(
+        DEFAULT_SECRETS.iter().map(|p| Regex::new(p).unwrap()).collect()
+    );
+}
+
+#[derive(Clone)]
+pub struct Entry {
+    pub time: i64,
+    pub session: String,
+    pub cwd: String,
+    pub duration: f64,
+    pub status: i32,
+    pub command: String,
+}
+
+fn db_path() -> Option<String> {
+    env::var("HOME").ok().map(|home| home + "/.sbsh_history")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn parse_line(line: &str) -> Option<Entry> {
+    let mut parts = line.splitn(6, '\t');
+    Some(Entry {
+        time: parts.next()?.parse().ok()?,
+        session: parts.next()?.to_string(),
+        cwd: unescape(parts.next()?),
+        duration: parts.next()?.parse().ok()?,
+        status: parts.next()?.parse().ok()?,
+        command: unescape(parts.next()?),
+    })
+}
+
+fn format_line(entry: &Entry) -> String {
+    format!("{}\t{}\t{}\t{:.3}\t{}\t{}\n",
+        entry.time, entry.session, escape(&entry.cwd), entry.duration, entry.status, escape(&entry.command))
+}
+
+/// Holds an flock on the file while it is alive; other shells wait for it
+struct Lock<'a>(&'a File);
+
+impl<'a> Lock<'a> {
+    fn new(file: &'a File, exclusive: bool) -> Self {
+        unsafe {
+            libc::flock(file.as_raw_fd(), if exclusive { libc::LOCK_EX } else { libc::LOCK_SH });
+        }
+        Lock(file)
+    }
+}
+
+impl Drop for Lock<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            libc::flock(self.0.as_raw_fd(), libc::LOCK_UN);
+        }
+    }
+}
+
+/// Every entry in the file, including those written by other shells
+pub fn read_all() -> Vec<Entry> {
+    let Some(path) = db_path() else {
+        return Vec::new();
+    };
+    let Ok(file) = File::open(&path) else {
+        return Vec::new();
+    };
+    let mut content = String::new();
+    {
+        let _lock = Lock::new(&file, false);
+        let _ = (&file).read_to_string(&mut content);
+    }
+    content.lines().filter_map(parse_line).collect()
+}
+
+/// Imports the plain text history of older versions, once
+fn import_old(path: &str) {
+    let Ok(home) = env::var("HOME") else {
+        return;
+    };
+    let Ok(old) = fs::read_to_string(home + "/.sbsh_history.txt") else {
+        return;
+    };
+    let content: String = old.lines()
+        .filter(|line| !line.is_empty() && *line != "#V2")
+        .map(|line| format_line(&Entry {
+            time: 0,
+            session: String::new(),
+            cwd: String::new(),
+            duration: 0.0,
+            status: 0,
+            command: line.to_string(),
+        }))
+        .collect();
+    let _ = fs::write(path, content);
+}
+
+fn max_size() -> usize {
+    env::var("HISTSIZE").ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_SIZE)
+}
+
+/// Keeps only the newest `max` entries in the file
+fn trim(max: usize) {
+    let Some(path) = db_path() else {
+        return;
+    };
+    let Ok(file) = OpenOptions::new().read(true).write(true).open(path) else {
+        return;
+    };
+    let _lock = Lock::new(&file, true);
+    let mut content = String::new();
+    let _ = (&file).read_to_string(&mut content);
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.len() <= max {
+        return;
+    }
+    // Rewritten in place, other shells keep appending to the same file
+    let kept: String = lines[lines.len() - max..].iter().map(|l| format!("{}\n", l)).collect();
+    let _ = file.set_len(0);
+    let _ = (&file).seek(SeekFrom::Start(0));
+    let _ = (&file).write_all(kept.as_bytes());
+}
+
+/// Loads the database at startup and returns the commands, oldest first
+pub fn load() -> Vec<String> {
+    if let Some(path) = db_path()
+        && !Path::new(&path).exists() {
+        import_old(&path);
+    }
+    trim(max_size());
+    let entries = read_all();
+    let commands = entries.iter().map(|e| e.command.clone()).collect();
+    *ENTRIES.lock().unwrap() = entries;
+    commands
+}
+
+/// Entry written before its command ran, completed by finish()
+struct Running {
+    entry: Entry,
+    //Where the line was written
+    offset: u64,
+    started: Instant,
+}
+
+/// Appends a command to the database before it runs, with status -1 until finish()
+/// is called; a command the shell never saw finishing (it was killed) keeps -1
+pub fn add(command: &str, cwd: &str) {
+    let entry = Entry {
+        time: Utc::now().timestamp(),
+        session: SESSION.clone(),
+        cwd: cwd.to_string(),
+        duration: 0.0,
+        status: -1,
+        command: command.to_string(),
+    };
+    let mut offset = 0;
+    if let Some(path) = db_path()
+        && let Ok(file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _lock = Lock::new(&file, true);
+        offset = file.metadata().map(|m| m.len()).unwrap_or(0);
+        // One write per entry so lines of concurrent shells never interleave
+        let _ = (&file).write_all(format_line(&entry).as_bytes());
+    }
+    let mut entries = ENTRIES.lock().unwrap();
+    entries.push(entry.clone());
+    let extra = entries.len().saturating_sub(max_size());
+    entries.drain(..extra);
+    *RUNNING.lock().unwrap() = Some(Running { entry, offset, started: Instant::now() });
+}
+
+/// Fills in the duration and status of the entry written by add(), if any
+pub fn finish(status: i32) {
+    let Some(running) = RUNNING.lock().unwrap().take() else {
+        return;
+    };
+    let old = format_line(&running.entry);
+    let entry = Entry { duration: running.started.elapsed().as_secs_f64(), status, ..running.entry };
+    if let Some(path) = db_path()
+        && let Ok(file) = OpenOptions::new().read(true).write(true).open(path) {
+        let _lock = Lock::new(&file, true);
+        let mut content = String::new();
+        let _ = (&file).read_to_string(&mut content);
+        // The line is normally where it was written, unless another shell trimmed the file since
+        let offset = running.offset as usize;
+        let at = if content.get(offset..).is_some_and(|rest| rest.starts_with(&old)) {
+            Some(offset)
+        } else {
+            content.rmatch_indices(&old).map(|(i, _)| i).find(|&i| i == 0 || content.as_bytes()[i - 1] == b'\n')
+        };
+        if let Some(at) = at {
+            // Everything after the line is written again, usually nothing
+            let tail = format_line(&entry) + &content[at + old.len()..];
+            let _ = file.set_len(at as u64);
+            let _ = (&file).seek(SeekFrom::Start(at as u64));
+            let _ = (&file).write_all(tail.as_bytes());
+        }
+    }
+    let mut entries = ENTRIES.lock().unwrap();
+    if let Some(last) = entries.iter_mut().rev()
+        .find(|e| e.time == entry.time && e.session == entry.session && e.command == entry.command) {
+        *last = entry;
+    }
+}
+
+/// Adds a secret pattern for history redaction
+pub fn add_secret(pattern: &str) -> Result<(), String> {
+    let re = Regex::new(pattern).map_err(|e| format!("invalid regex '{}': {}", pattern, e))?;
+    SECRETS.lock().unwrap().push(re);
+    Ok(())
+}
+
+pub fn clear_secrets() {
+    SECRETS.lock().unwrap().clear();
+}
+
+/// Replaces secrets in `line` with ***
+fn redact(line: &str) -> String {
+    let mut out = line.to_string();
+    for re in SECRETS.lock().unwrap().iter() {
+        out = re.replace_all(&out, |caps: &Captures| {
+            let whole = caps.get(0).unwrap();
+            match caps.get(1) {
+                Some(secret) => format!("{}***{}",
+                    &whole.as_str()[..secret.start() - whole.start()],
+                    &whole.as_str()[secret.end() - whole.start()..]),
+                None => "***".to_string(),
+            }
+        }).into_owned();
+    }
+    out
+}
+
+/// Asks the should_save_history(line) hook; saves if there is no hook
+fn hook_allows(line: &str) -> bool {
+    crate::GLOBAL_ENGINE.with(|eng| {
+        crate::GLOBAL_AST.with(|cell| {
+            let ast = cell.borrow();
+            let Some(ast) = ast.as_ref() else {
+                return true;
+            };
+            let mut scope = rhai::Scope::new();
+            match eng.borrow().call_fn_with_options::<rhai::Dynamic>(crate::rhai_api::hook_options(), &mut scope, ast, "should_save_history", (line.to_string(),)) {
+                Ok(value) => value.as_bool().unwrap_or(true),
+                Err(e) => {
+                    if !crate::rhai_api::is_missing_hook(&e, "should_save_history") {
+                        eprintln!("Error in should_save_history hook: {}", e);
+                    }
+                    true
+                }
+            }
+        })
+    })
+}
+
+/// The line as it should be saved in history, None if it should not be saved:
+/// HISTIGNORE patterns, histignorespace, histignoredups, the should_save_history hook
+/// and secret redaction
+pub fn filter(line: &str) -> Option<String> {
+    if line.trim().is_empty() || (options::get("histignorespace") && line.starts_with(' ')) {
+        return None;
+    }
+    let ignore = env::var("HISTIGNORE").unwrap_or_default();
+    if ignore.split(':').any(|p| !p.is_empty() && conditions::glob_match(p, line.trim())) {
+        return None;
+    }
+    let line = redact(line);
+    if options::get("histignoredups") && with_entries(|e| e.last().is_some_and(|last| last.command == line)) {
+        return None;
+    }
+    hook_allows(&line).then_some(line)
+}
+
+/// Runs `f` on the entries known to this shell, oldest first
+pub fn with_entries<R>(f: impl FnOnce(&[Entry]) -> R) -> R {
+    f(&ENTRIES.lock().unwrap())
+}
+
+/// Parses "30m", "2h", "1d", "1w" (ago) or "YYYY-MM-DD [HH:MM[:SS]]"
+fn parse_time(text: &str) -> Option<i64> {
+    let now = Utc::now().timestamp();
+    if let Some(unit) = text.chars().last()
+        && let Ok(n) = text[..text.len() - unit.len_utf8()].parse::<i64>() {
+        let seconds = match unit {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604800,
+            _ => return None,
+        };
+        return Some(now - n * seconds);
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"].iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(text, f).ok())
+        .or_else(|| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))?;
+    Local.from_local_datetime(&naive).earliest().map(|t| t.timestamp())
+}
+
+fn format_time(time: i64) -> String {
+    if time == 0 {
+        return "-".repeat(19);
+    }
+    Local.timestamp_opt(time, 0).single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+#[derive(Default)]
+struct Filter {
+    count: Option<usize>,
+    dir: Option<String>,
+    status: Option<i32>,
+    failed: bool,
+    since: Option<i64>,
+    until: Option<i64>,
+    session: bool,
+    long: bool,
+    pattern: Option<String>,
+}
+
+fn parse_filter(args: &[String]) -> Result<Filter, String> {
+    let mut filter = Filter::default();
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| iter.next().cloned().ok_or(format!("{}: option requires an argument", name));
+        match arg.as_str() {
+            "-n" => filter.count = Some(value("-n")?.parse().map_err(|_| "-n: invalid number".to_string())?),
+            "-d" => {
+                let dir = value("-d")?;
+                let dir = fs::canonicalize(&dir).map(|p| p.to_string_lossy().to_string()).unwrap_or(dir);
+                filter.dir = Some(dir);
+            }
+            "--here" => filter.dir = Some(crate::api::get_current_dir()),
+            "-s" => filter.status = Some(value("-s")?.parse().map_err(|_| "-s: invalid status".to_string())?),
+            "--failed" => filter.failed = true,
+            "--since" => filter.since = Some(parse_time(&value("--since")?).ok_or("--since: invalid time")?),
+            "--until" => filter.until = Some(parse_time(&value("--until")?).ok_or("--until: invalid time")?),
+            "--session" => filter.session = true,
+            "-l" => filter.long = true,
+            _ if arg.starts_with('-') => return Err(format!("{}: invalid option", arg)),
+            _ if arg.chars().all(|c| c.is_ascii_digit()) => filter.count = arg.parse().ok(),
+            _ => filter.pattern = Some(arg.clone()),
+        }
+    }
+    Ok(filter)
+}
+
+/// History builtin: history [-n N | N] [-d dir | --here] [-s status | --failed]
+/// [--since time] [--until time] [--session] [-l] [text]
+pub fn handle_history(args: Vec<String>) -> i32 {
+    let filter = match parse_filter(&args) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("history: {}", e);
+            return 2;
+        }
+    };
+
+    let matched: Vec<(usize, Entry)> = read_all().into_iter()
+        .enumerate()
+        .map(|(i, e)| (i + 1, e))
+        .filter(|(_, e)| {
+            filter.dir.as_ref().is_none_or(|d| e.cwd == *d)
+                && filter.status.is_none_or(|s| e.status == s)
+                && (!filter.failed || e.status != 0)
+                && filter.since.is_none_or(|t| e.time >= t)
+                && filter.until.is_none_or(|t| e.time <= t)
+                && (!filter.session || e.session == *SESSION)
+                && filter.pattern.as_ref().is_none_or(|p| e.command.contains(p.as_str()))
+        })
+        .collect();
+    let skip = filter.count.map(|n| matched.len().saturating_sub(n)).unwrap_or(0);
+
+    for (n, e) in matched.iter().skip(skip) {
+        if filter.long {
+            println!("{:>5}  {}  {:>8.2}s  {:>3}  {}  {}", n, format_time(e.time), e.duration, e.status, e.cwd, e.command);
+        } else {
+            println!("{:>5}  {}  {}", n, format_time(e.time), e.command);
+        }
+    }
+    0
+}
+
+/// Words of a command as typed, quotes kept
+fn raw_words(command: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in command.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (c, quote) {
+            ('\\', q) if q != Some('\'') => escaped = true,
+            ('\'', None) | ('"', None) => quote = Some(c),
+            (q, Some(open)) if q == open => quote = None,
+            (c, None) if c.is_whitespace() => {
+                if let Some(s) = start.take() {
+                    words.push(&command[s..i]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(s) = start {
+        words.push(&command[s..]);
+    }
+    words
+}
+
+/// Previous command of this shell; `back` = 1 is the last one
+fn previous(back: usize) -> Option<String> {
+    with_entries(|entries| entries.len().checked_sub(back).map(|i| entries[i].command.clone()))
+}
+
+/// Resolves the event after `!` at the start of `rest`; returns the text and how much of `rest` it used
+fn event(rest: &str) -> Result<(String, usize), String> {
+    let not_found = |text: &str| format!("!{}: event not found", text);
+    let last = || previous(1).ok_or_else(|| not_found("!"));
+    let word_end = rest.find(|c: char| c.is_whitespace() || c == ';' || c == '|').unwrap_or(rest.len());
+
+    match rest.chars().next() {
+        Some('!') => Ok((last()?, 1)),
+        Some('$') => Ok((raw_words(&last()?).last().unwrap_or(&"").to_string(), 1)),
+        Some('^') => Ok((raw_words(&last()?).get(1).unwrap_or(&"").to_string(), 1)),
+        Some('*') => Ok((raw_words(&last()?).get(1..).unwrap_or(&[]).join(" "), 1)),
+        Some('-') => {
+            let digits = rest[1..].find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len() - 1);
+            let text = &rest[..digits + 1];
+            let back: usize = rest[1..digits + 1].parse().map_err(|_| not_found(text))?;
+            Ok((previous(back).filter(|_| back > 0).ok_or_else(|| not_found(text))?, digits + 1))
+        }
+        Some(c) if c.is_ascii_digit() => {
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            let text = &rest[..digits];
+            // Same numbers as the history builtin shows
+            let n: usize = text.parse().map_err(|_| not_found(text))?;
+            let entry = read_all().into_iter().nth(n.wrapping_sub(1)).ok_or_else(|| not_found(text))?;
+            Ok((entry.command, digits))
+        }
+        _ => {
+            let prefix = &rest[..word_end];
+            let found = with_entries(|entries| {
+                entries.iter().rev().find(|e| e.command.starts_with(prefix)).map(|e| e.command.clone())
+            });
+            Ok((found.ok_or_else(|| not_found(prefix))?, word_end))
+        }
+    }
+}
+
+/// ^old^new[^]: the previous command with the first `old` replaced by `new`
+fn quick_substitution(line: &str) -> Result<String, String> {
+    let mut parts = line[1..].splitn(3, '^');
+    let old = parts.next().unwrap_or_default();
+    let new = parts.next().unwrap_or_default();
+    let tail = parts.next().unwrap_or_default();
+    let last = previous(1).ok_or("^: no previous command")?;
+    if old.is_empty() || !last.contains(old) {
+        return Err(format!("^{}^{}: substitution failed", old, new));
+    }
+    Ok(last.replacen(old, new, 1) + tail)
+}
+
+/// History expansion: !!, !$, !^, !*, !n, !-n, !prefix and ^old^new.
+/// Returns None if the line has nothing to expand
+pub fn expand(line: &str) -> Result<Option<String>, String> {
+    if line.starts_with('^') {
+        return quick_substitution(line).map(Some);
+    }
+    if !line.contains('!') {
+        return Ok(None);
+    }
+
+    let mut out = String::new();
+    let mut changed = false;
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut escaped = false;
+    let mut i = 0;
+    while i < line.len() {
+        let c = line[i..].chars().next().unwrap();
+        i += c.len_utf8();
+        if escaped {
+            escaped = false;
+            out.push(c);
+            continue;
+        }
+        match c {
+            '\\' if !in_single => escaped = true,
+            '\'' if !in_double => in_single = !in_single,
+            '"' if !in_single => in_double = !in_double,
+            '!' if !in_single && !out.ends_with('$') => {
+                let rest = &line[i..];
+                // A lone ! (as in `test ! -e file`) stays as it is
+                if !rest.is_empty() && !rest.starts_with(|c: char| c.is_whitespace() || c == '=' || c == '(' || c == '"') {
+                    let (text, used) = event(rest)?;
+                    out.push_str(&text);
+                    i += used;
+                    changed = true;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+    Ok(changed.then_some(out))
+}
@@ -0,0 +1,133 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Shell options: set, setopt and unsetopt builtins
+use std::collections::HashMap;
+use std::sync::Mutex;
+use lazy_static::lazy_static;
+
+/// Known options: long name, short flag used by `set`, default value
+const KNOWN: &[(&str, Option<char>, bool)] = &[
+    ("errexit", Some('e'), false),
+    ("nounset", Some('u'), false),
+    ("xtrace", Some('x'), false),
+    ("pipefail", None, false),
+    ("noclobber", Some('C'), false),
+    ("autocd", None, false),
+    ("histexpand", Some('H'), true),
+    ("histverify", None, false),
+    ("histignorespace", None, false),
+    ("histignoredups", None, false),
+    ("transientprompt", None, false),
+];
+
+lazy_static! {
+    static ref OPTIONS: Mutex<HashMap<String, bool>> = Mutex::new(
+        KNOWN.iter().map(|(name, _, default)| (name.to_string(), *default)).collect()
+    );
+}
+
+pub fn get(name: &str) -> bool {
+    OPTIONS.lock().unwrap().get(name).copied().unwrap_or(false)
+}
+
+/// Returns false if the option is unknown
+pub fn set(name: &str, value: bool) -> bool {
+    let mut options = OPTIONS.lock().unwrap();
+    match options.get_mut(name) {
+        Some(v) => {
+            *v = value;
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn list() -> Vec<(String, bool)> {
+    let options = OPTIONS.lock().unwrap();
+    KNOWN.iter()
+        .map(|(name, _, _)| (name.to_string(), options.get(*name).copied().unwrap_or(false)))
+        .collect()
+}
+
+fn by_flag(flag: char) -> Option<&'static str> {
+    KNOWN.iter()
+        .find(|(_, short, _)| *short == Some(flag))
+        .map(|(name, _, _)| *name)
+}
+
+fn print_options(as_commands: bool) {
+    for (name, value) in list() {
+        if as_commands {
+            println!("set {}o {}", if value { '-' } else { '+' }, name);
+        } else {
+            println!("{:<12}{}", name, if value { "on" } else { "off" });
+        }
+    }
+}
+
+/// Set builtin: set [-eux] [+eux] [-o name] [+o name]
+pub fn handle_set(args: Vec<String>) -> i32 {
+    if args.len() < 2 {
+        print_options(false);
+        return 0;
+    }
+
+    let mut i = 1;
+    while i < args.len() {
+        let arg = &args[i];
+        let enable = arg.starts_with('-');
+        if !enable && !arg.starts_with('+') {
+            eprintln!("set: {}: invalid argument", arg);
+            return 2;
+        }
+
+        if arg == "-o" || arg == "+o" {
+            match args.get(i + 1) {
+                Some(name) => {
+                    if !set(name, enable) {
+                        eprintln!("set: {}: invalid option name", name);
+                        return 2;
+                    }
+                    i += 1;
+                }
+                None => print_options(!enable),
+            }
+        } else {
+            for flag in arg.chars().skip(1) {
+                match by_flag(flag) {
+                    Some(name) => {
+                        set(name, enable);
+                    }
+                    None => {
+                        eprintln!("set: {}{}: invalid option", &arg[..1], flag);
+                        return 2;
+                    }
+                }
+            }
+        }
+        i += 1;
+    }
+    0
+}
+
+/// Setopt/unsetopt builtins: setopt [name...], unsetopt name...
+pub fn handle_setopt(args: Vec<String>, enable: bool) -> i32 {
+    if args.len() < 2 {
+        for (name, value) in list() {
+            if value == enable {
+                println!("{}", name);
+            }
+        }
+        return 0;
+    }
+
+    let mut status = 0;
+    for name in args.iter().skip(1) {
+        if !set(name, enable) {
+            eprintln!("{}: no such option: {}", args[0], name);
+            status = 1;
+        }
+    }
+    status
+}
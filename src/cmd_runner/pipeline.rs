@@ -0,0 +1,298 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Pipelines (a | b) and redirections (<, >, >>, >|, 2>, 2>>, 2>&1)
+use libc::{c_int, close, dup, dup2, fork, pipe};
+use std::fs::{File, OpenOptions};
+use std::os::unix::io::IntoRawFd;
+use std::path::Path;
+use super::{options, run_builtin, exec_child, timing, wait_status};
+
+enum Redirect {
+    Input(String),
+    Output(String),
+    Append(String),
+    Clobber(String),
+    Error(String),
+    ErrorAppend(String),
+    ErrorToOutput,
+}
+
+struct Stage {
+    args: Vec<String>,
+    redirects: Vec<Redirect>,
+}
+
+//Longest operators first so ">>" is not read as ">"
+pub const OPERATORS: &[&str] = &["2>&1", "2>>", "2>", ">>", ">|", ">", "<"];
+
+pub fn is_operator(arg: &str) -> bool {
+    arg == "|" || OPERATORS.iter().any(|op| arg.starts_with(op))
+}
+
+/// Words of a command line as typed, quotes and backslashes kept; split the way shlex::split does
+pub fn raw_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some('\'') => {
+                word.push(c);
+                if c == '\'' {
+                    quote = None;
+                }
+            }
+            Some(_) => {
+                word.push(c);
+                if c == '\\' {
+                    word.extend(chars.next());
+                } else if c == '"' {
+                    quote = None;
+                }
+            }
+            None if matches!(c, ' ' | '\t' | '\n') => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            // A comment starts at the beginning of a word
+            None if c == '#' && word.is_empty() => break,
+            None => {
+                word.push(c);
+                match c {
+                    '\\' => word.extend(chars.next()),
+                    '\'' | '"' => quote = Some(c),
+                    _ => {}
+                }
+            }
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Marks which arguments are operators. Only words typed without quotes count (`">"` is text),
+/// and `<` and `>` inside `[[ ]]` are comparisons. `raw` are the words as typed, see raw_words
+fn operator_mask(args: &[String], raw: &[String]) -> Vec<bool> {
+    // If the typed words do not line up, quoting is unknown: nothing is taken as an operator
+    if raw.len() != args.len() {
+        return vec![false; args.len()];
+    }
+    let mut in_condition = false;
+    let mut command_start = true;
+    args.iter().zip(raw)
+        .map(|(arg, typed)| {
+            if command_start && arg == "[[" {
+                in_condition = true;
+            } else if in_condition && arg == "]]" {
+                in_condition = false;
+                return false;
+            }
+            let operator = !in_condition && is_operator(typed);
+            command_start = operator && arg == "|";
+            operator
+        })
+        .collect()
+}
+
+/// True if the arguments need the pipeline runner instead of a plain call
+pub fn is_needed(args: &[String], raw: &[String]) -> bool {
+    operator_mask(args, raw).into_iter().any(|op| op)
+}
+
+fn parse(args: Vec<String>, raw: &[String]) -> Result<Vec<Stage>, String> {
+    let mut stages = vec![Stage { args: Vec::new(), redirects: Vec::new() }];
+    let mask = operator_mask(&args, raw);
+    let mut iter = args.into_iter().zip(mask);
+
+    while let Some((arg, operator)) = iter.next() {
+        if !operator {
+            stages.last_mut().unwrap().args.push(arg);
+            continue;
+        }
+        if arg == "|" {
+            if stages.last().is_some_and(|s| s.args.is_empty()) {
+                return Err("syntax error near '|'".to_string());
+            }
+            stages.push(Stage { args: Vec::new(), redirects: Vec::new() });
+            continue;
+        }
+
+        let op = OPERATORS.iter().find(|op| arg.starts_with(**op)).copied().unwrap_or(">");
+
+        let redirect = if op == "2>&1" {
+            Redirect::ErrorToOutput
+        } else {
+            let target = if arg.len() > op.len() {
+                arg[op.len()..].to_string()
+            } else {
+                iter.next().map(|(target, _)| target).ok_or_else(|| format!("syntax error near '{}'", op))?
+            };
+            match op {
+                "<" => Redirect::Input(target),
+                ">" => Redirect::Output(target),
+                ">>" => Redirect::Append(target),
+                ">|" => Redirect::Clobber(target),
+                "2>" => Redirect::Error(target),
+                _ => Redirect::ErrorAppend(target),
+            }
+        };
+        stages.last_mut().unwrap().redirects.push(redirect);
+    }
+
+    if stages.iter().any(|s| s.args.is_empty()) {
+        return Err("syntax error: empty command".to_string());
+    }
+    Ok(stages)
+}
+
+fn open_output(path: &str, append: bool, force: bool) -> Result<File, String> {
+    if !append && !force && options::get("noclobber") && Path::new(path).is_file() {
+        return Err(format!("{}: cannot overwrite existing file", path));
+    }
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Points fds 0/1/2 at the redirect targets of the current process
+fn apply_redirects(redirects: &[Redirect]) -> Result<(), String> {
+    for redirect in redirects {
+        let (fd, target) = match redirect {
+            Redirect::Input(path) => {
+                let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+                (file.into_raw_fd(), 0)
+            }
+            Redirect::Output(path) => (open_output(path, false, false)?.into_raw_fd(), 1),
+            Redirect::Append(path) => (open_output(path, true, false)?.into_raw_fd(), 1),
+            Redirect::Clobber(path) => (open_output(path, false, true)?.into_raw_fd(), 1),
+            Redirect::Error(path) => (open_output(path, false, false)?.into_raw_fd(), 2),
+            Redirect::ErrorAppend(path) => (open_output(path, true, false)?.into_raw_fd(), 2),
+            Redirect::ErrorToOutput => {
+                unsafe { dup2(1, 2); }
+                continue;
+            }
+        };
+        unsafe {
+            dup2(fd, target);
+            close(fd);
+        }
+    }
+    Ok(())
+}
+
+/// Runs a builtin in the shell process itself so `cd dir > log` still changes directory
+fn run_single(stage: Stage) -> i32 {
+    if !super::is_builtin(&stage.args[0]) {
+        return run_stages(vec![stage]);
+    }
+
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+    let saved: Vec<c_int> = (0..3).map(|fd| unsafe { dup(fd) }).collect();
+    let status = match apply_redirects(&stage.redirects) {
+        Ok(()) => run_builtin(stage.args).unwrap_or(0),
+        Err(e) => {
+            eprintln!("sbsh: {}", e);
+            1
+        }
+    };
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+    let _ = std::io::Write::flush(&mut std::io::stderr());
+    for (fd, copy) in saved.into_iter().enumerate() {
+        unsafe {
+            dup2(copy, fd as c_int);
+            close(copy);
+        }
+    }
+    status
+}
+
+fn run_stages(stages: Vec<Stage>) -> i32 {
+    let count = stages.len();
+    let mut children = Vec::with_capacity(count);
+    let mut prev_read: c_int = -1;
+
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+    for (i, stage) in stages.into_iter().enumerate() {
+        let mut fds: [c_int; 2] = [-1, -1];
+        if i + 1 < count && unsafe { pipe(fds.as_mut_ptr()) } == -1 {
+            eprintln!("sbsh: pipe failed");
+            break;
+        }
+
+        let pid = unsafe { fork() };
+        match pid {
+            -1 => {
+                eprintln!("sbsh: fork failed");
+                break;
+            }
+            0 => unsafe {
+                if prev_read != -1 {
+                    dup2(prev_read, 0);
+                    close(prev_read);
+                }
+                if fds[1] != -1 {
+                    dup2(fds[1], 1);
+                    close(fds[1]);
+                    close(fds[0]);
+                }
+                if let Err(e) = apply_redirects(&stage.redirects) {
+                    eprintln!("sbsh: {}", e);
+                    libc::_exit(1);
+                }
+                exec_child(stage.args);
+            },
+            child => children.push(child),
+        }
+
+        unsafe {
+            if prev_read != -1 {
+                close(prev_read);
+            }
+            if fds[1] != -1 {
+                close(fds[1]);
+            }
+        }
+        prev_read = fds[0];
+    }
+
+    let mut statuses = Vec::with_capacity(children.len());
+    for child in children {
+        let mut status: c_int = 0;
+        if timing::wait_child(child, &mut status) == -1 {
+            statuses.push(1);
+        } else {
+            statuses.push(wait_status(status));
+        }
+    }
+
+    if options::get("pipefail") {
+        statuses.iter().rev().find(|s| **s != 0).copied().unwrap_or(0)
+    } else {
+        statuses.last().copied().unwrap_or(1)
+    }
+}
+
+/// Runs a command line that contains pipes or redirections, returns its exit status
+pub fn run(args: Vec<String>, raw: &[String]) -> i32 {
+    let mut stages = match parse(args, raw) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("sbsh: {}", e);
+            return 2;
+        }
+    };
+
+    if stages.len() == 1 {
+        return run_single(stages.remove(0));
+    }
+    run_stages(stages)
+}
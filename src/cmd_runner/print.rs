@@ -1,11 +1,14 @@
 // Copyright (C) <2026> <Bogdan Yachmenv>
 // SPDX-License-Identifier: GPL-3.0/
-use std::env;
 
-pub fn print(args: Vec<String>) {
+//Output builtins: print, echo and printf
+//Variables are already expanded by cmd_runner before they are called
+use std::io::Write;
+
+pub fn print(args: Vec<String>) -> i32 {
     if args.len() < 2 {
         eprintln!("print: missing arguments");
-        return;
+        return 1;
     }
 
     let mut output = Vec::new();
@@ -14,17 +17,432 @@ pub fn print(args: Vec<String>) {
         if arg == ";" {
             break;
         }
+        output.push(arg.clone());
+    }
+    println!("{}", output.join(" "));
+    0
+}
+
+/// Echo builtin: echo [-n] [-e] [-E] [arguments...]
+pub fn echo(args: Vec<String>) -> i32 {
+    let mut newline = true;
+    let mut escapes = false;
+    let mut start = 1;
+
+    // Leading words made only of n/e/E flags are options, anything else is text
+    for arg in args.iter().skip(1) {
+        let Some(flags) = arg.strip_prefix('-') else { break };
+        if flags.is_empty() || !flags.chars().all(|c| matches!(c, 'n' | 'e' | 'E')) {
+            break;
+        }
+        for flag in flags.chars() {
+            match flag {
+                'n' => newline = false,
+                'e' => escapes = true,
+                _ => escapes = false,
+            }
+        }
+        start += 1;
+    }
+
+    let text = args[start.min(args.len())..].join(" ");
+    let mut out = std::io::stdout();
+    if escapes {
+        let (text, stop) = unescape(&text);
+        let _ = out.write_all(text.as_bytes());
+        if stop {
+            let _ = out.flush();
+            return 0;
+        }
+    } else {
+        let _ = out.write_all(text.as_bytes());
+    }
+    if newline {
+        let _ = out.write_all(b"\n");
+    }
+    let _ = out.flush();
+    0
+}
+
+/// Expands backslash escapes; the flag is true if `\c` asked to stop all output
+fn unescape(text: &str) -> (String, bool) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
 
-        if let Some(var_name) = arg.strip_prefix('$') {
-            match env::var(var_name) {
-                Ok(val) => output.push(val),
-                Err(_) => {
-                    eprintln!("Varriable not searched!");
+    while i < chars.len() {
+        if chars[i] != '\\' || i + 1 == chars.len() {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        match chars[i] {
+            'a' => out.push('\x07'),
+            'b' => out.push('\x08'),
+            'c' => return (out, true),
+            'e' | 'E' => out.push('\x1b'),
+            'f' => out.push('\x0c'),
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            't' => out.push('\t'),
+            'v' => out.push('\x0b'),
+            '\\' => out.push('\\'),
+            '0' => {
+                let (value, len) = read_digits(&chars[i + 1..], 8, 3);
+                out.push(char::from_u32(value).unwrap_or('\0'));
+                i += len;
+            }
+            'x' | 'u' | 'U' => {
+                let max = match chars[i] { 'x' => 2, 'u' => 4, _ => 8 };
+                let (value, len) = read_digits(&chars[i + 1..], 16, max);
+                if len == 0 {
+                    out.push('\\');
+                    out.push(chars[i]);
+                } else {
+                    out.push(char::from_u32(value).unwrap_or('\u{FFFD}'));
+                    i += len;
                 }
             }
+            other => {
+                out.push('\\');
+                out.push(other);
+            }
+        }
+        i += 1;
+    }
+    (out, false)
+}
+
+fn read_digits(chars: &[char], radix: u32, max: usize) -> (u32, usize) {
+    let mut value = 0u32;
+    let mut len = 0;
+    while len < max {
+        match chars.get(len).and_then(|c| c.to_digit(radix)) {
+            Some(d) => value = value.wrapping_mul(radix).wrapping_add(d),
+            None => break,
+        }
+        len += 1;
+    }
+    (value, len)
+}
+
+/// One parsed %-specifier
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    zero: bool,
+    plus: bool,
+    space: bool,
+    alt: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+/// Printf builtin: printf format [arguments...]
+/// The format is repeated until every argument is used
+pub fn printf(args: Vec<String>) -> i32 {
+    let Some(format) = args.get(1) else {
+        eprintln!("printf: usage: printf format [arguments]");
+        return 2;
+    };
+    let format: Vec<char> = format.chars().collect();
+    let values = &args[2..];
+    let mut next = 0;
+    let mut status = 0;
+    let mut out = String::new();
+
+    loop {
+        let before = next;
+        if render(&format, values, &mut next, &mut out, &mut status) {
+            break;
+        }
+        if next == before || next >= values.len() {
+            break;
+        }
+    }
+
+    let mut stdout = std::io::stdout();
+    let _ = stdout.write_all(out.as_bytes());
+    let _ = stdout.flush();
+    status
+}
+
+/// Renders the format once; returns true if `\c` stopped the output
+fn render(format: &[char], values: &[String], next: &mut usize, out: &mut String, status: &mut i32) -> bool {
+    let mut take = || {
+        let value = values.get(*next).cloned();
+        *next += 1;
+        value
+    };
+    let mut i = 0;
+
+    while i < format.len() {
+        let c = format[i];
+        if c == '\\' {
+            // Escapes in the format itself, one at a time
+            let end = escape_len(&format[i..]);
+            let escape: String = format[i..i + end].iter().collect();
+            let (text, stop) = unescape(&escape);
+            out.push_str(&text);
+            if stop {
+                return true;
+            }
+            i += end;
+            continue;
+        }
+        if c != '%' {
+            out.push(c);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        if format.get(i) == Some(&'%') {
+            out.push('%');
+            i += 1;
+            continue;
+        }
+
+        let mut spec = Spec::default();
+        while let Some(flag) = format.get(i) {
+            match flag {
+                '-' => spec.left = true,
+                '0' => spec.zero = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                '#' => spec.alt = true,
+                _ => break,
+            }
+            i += 1;
+        }
+        if format.get(i) == Some(&'*') {
+            let width = to_int(&take().unwrap_or_default(), status);
+            spec.left |= width < 0;
+            spec.width = width.unsigned_abs() as usize;
+            i += 1;
         } else {
-            output.push(arg.clone());
+            spec.width = read_number(format, &mut i);
+        }
+        if format.get(i) == Some(&'.') {
+            i += 1;
+            if format.get(i) == Some(&'*') {
+                spec.precision = Some(to_int(&take().unwrap_or_default(), status).max(0) as usize);
+                i += 1;
+            } else {
+                spec.precision = Some(read_number(format, &mut i));
+            }
         }
+
+        let Some(&conversion) = format.get(i) else {
+            eprintln!("printf: missing format character");
+            *status = 1;
+            return true;
+        };
+        i += 1;
+        let value = take();
+        let text = match conversion {
+            's' => {
+                let s = value.unwrap_or_default();
+                match spec.precision {
+                    Some(p) => s.chars().take(p).collect(),
+                    None => s,
+                }
+            }
+            'b' => {
+                let (text, stop) = unescape(&value.unwrap_or_default());
+                out.push_str(&pad(&text, &spec, false));
+                if stop {
+                    return true;
+                }
+                continue;
+            }
+            'q' => {
+                let s = value.unwrap_or_default();
+                shlex::try_quote(&s).map(|q| q.into_owned()).unwrap_or(s)
+            }
+            'c' => value.unwrap_or_default().chars().next().map(String::from).unwrap_or_default(),
+            'd' | 'i' => {
+                let n = to_int(&value.unwrap_or_default(), status);
+                let digits = min_digits(n.unsigned_abs().to_string(), spec.precision);
+                signed(n < 0, digits, &spec)
+            }
+            'u' | 'x' | 'X' | 'o' => {
+                let n = to_int(&value.unwrap_or_default(), status) as u64;
+                let digits = match conversion {
+                    'u' => n.to_string(),
+                    'x' => format!("{:x}", n),
+                    'X' => format!("{:X}", n),
+                    _ => format!("{:o}", n),
+                };
+                let digits = min_digits(digits, spec.precision);
+                let prefix = match conversion {
+                    'x' if spec.alt && n != 0 => "0x",
+                    'X' if spec.alt && n != 0 => "0X",
+                    'o' if spec.alt && !digits.starts_with('0') => "0",
+                    _ => "",
+                };
+                format!("{}{}", prefix, digits)
+            }
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                let n = to_float(&value.unwrap_or_default(), status);
+                let digits = format_float(n.abs(), conversion, spec.precision.unwrap_or(6), spec.alt);
+                signed(n.is_sign_negative() && n != 0.0, digits, &spec)
+            }
+            other => {
+                eprintln!("printf: %{}: invalid format character", other);
+                *status = 1;
+                return true;
+            }
+        };
+        let numeric = !matches!(conversion, 's' | 'q' | 'c');
+        out.push_str(&pad(&text, &spec, numeric));
+    }
+    false
+}
+
+/// Length of the escape sequence starting with '\' at chars[0]
+fn escape_len(chars: &[char]) -> usize {
+    match chars.get(1) {
+        Some('0') => 2 + read_digits(&chars[2..], 8, 3).1,
+        Some('x') => 2 + read_digits(&chars[2..], 16, 2).1,
+        Some('u') => 2 + read_digits(&chars[2..], 16, 4).1,
+        Some('U') => 2 + read_digits(&chars[2..], 16, 8).1,
+        Some(_) => 2,
+        None => 1,
+    }
+}
+
+fn read_number(format: &[char], i: &mut usize) -> usize {
+    let mut n = 0;
+    while let Some(d) = format.get(*i).and_then(|c| c.to_digit(10)) {
+        n = n * 10 + d as usize;
+        *i += 1;
+    }
+    n
+}
+
+/// Parses an integer argument like C printf: decimal, 0x hex, 0 octal or 'c for a char code
+fn to_int(value: &str, status: &mut i32) -> i64 {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return 0;
+    }
+    if let Some(c) = trimmed.strip_prefix('\'').or_else(|| trimmed.strip_prefix('"')) {
+        return c.chars().next().map(|c| c as i64).unwrap_or(0);
+    }
+    let (negative, digits) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let parsed = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        i64::from_str_radix(&digits[1..], 8)
+    } else {
+        digits.parse()
+    };
+    match parsed {
+        Ok(n) => if negative { -n } else { n },
+        Err(_) => {
+            eprintln!("printf: {}: invalid number", value);
+            *status = 1;
+            0
+        }
+    }
+}
+
+fn to_float(value: &str, status: &mut i32) -> f64 {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return 0.0;
+    }
+    trimmed.parse().unwrap_or_else(|_| {
+        let n = to_int(value, status);
+        n as f64
+    })
+}
+
+fn min_digits(digits: String, precision: Option<usize>) -> String {
+    match precision {
+        Some(p) if digits.len() < p => format!("{}{}", "0".repeat(p - digits.len()), digits),
+        _ => digits,
+    }
+}
+
+fn signed(negative: bool, digits: String, spec: &Spec) -> String {
+    let sign = if negative {
+        "-"
+    } else if spec.plus {
+        "+"
+    } else if spec.space {
+        " "
+    } else {
+        ""
+    };
+    format!("{}{}", sign, digits)
+}
+
+/// C-style %f, %e and %g of a non-negative number
+fn format_float(n: f64, conversion: char, precision: usize, alt: bool) -> String {
+    if !n.is_finite() {
+        let s = if n.is_nan() { "nan" } else { "inf" };
+        return if conversion.is_uppercase() { s.to_uppercase() } else { s.to_string() };
+    }
+    let exponent_form = |precision: usize| {
+        let s = format!("{:.*e}", precision, n);
+        let (mantissa, exp) = s.split_once('e').unwrap_or((&s, "0"));
+        let exp: i32 = exp.parse().unwrap_or(0);
+        format!("{}e{}{:02}", mantissa, if exp < 0 { '-' } else { '+' }, exp.abs())
+    };
+
+    let s = match conversion.to_ascii_lowercase() {
+        'f' => format!("{:.*}", precision, n),
+        'e' => exponent_form(precision),
+        _ => {
+            let p = precision.max(1);
+            let exp = if n == 0.0 { 0 } else { n.log10().floor() as i32 };
+            let s = if exp < -4 || exp >= p as i32 {
+                exponent_form(p - 1)
+            } else {
+                format!("{:.*}", (p as i32 - 1 - exp).max(0) as usize, n)
+            };
+            if alt { s } else { strip_zeros(s) }
+        }
+    };
+    if conversion.is_uppercase() { s.to_uppercase() } else { s }
+}
+
+///Removes trailing fraction zeros: 1.500 -> 1.5, 2.000e+00 -> 2e+00
+fn strip_zeros(s: String) -> String {
+    let (number, exponent) = match s.find('e') {
+        Some(pos) => (&s[..pos], &s[pos..]),
+        None => (s.as_str(), ""),
+    };
+    if !number.contains('.') {
+        return s;
+    }
+    let number = number.trim_end_matches('0').trim_end_matches('.');
+    format!("{}{}", number, exponent)
+}
+
+fn pad(text: &str, spec: &Spec, numeric: bool) -> String {
+    let len = text.chars().count();
+    if len >= spec.width {
+        return text.to_string();
+    }
+    let fill = spec.width - len;
+    if spec.left {
+        format!("{}{}", text, " ".repeat(fill))
+    } else if spec.zero && numeric {
+        // Zeros go after the sign and 0x prefix
+        let prefix_len = text.find(|c: char| c.is_ascii_digit()).unwrap_or(0);
+        let prefix_len = if text[prefix_len..].starts_with("0x") || text[prefix_len..].starts_with("0X") {
+            prefix_len + 2
+        } else {
+            prefix_len
+        };
+        format!("{}{}{}", &text[..prefix_len], "0".repeat(fill), &text[prefix_len..])
+    } else {
+        format!("{}{}", " ".repeat(fill), text)
     }
-    println!("{}", output.join(" "));
 }
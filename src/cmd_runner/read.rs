@@ -0,0 +1,269 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Read builtin and the input code shared with the prompt_input Rhai function
+use std::env;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use super::var;
+
+#[derive(Default)]
+pub struct ReadOptions {
+    pub prompt: String,
+    pub silent: bool,
+    pub raw: bool,
+    pub timeout: Option<Duration>,
+    pub max_chars: Option<usize>,
+}
+
+pub enum ReadError {
+    Eof,
+    //With what was typed before the timeout
+    Timeout(String),
+}
+
+/// Terminal settings changed for -s and -n, restored on drop
+struct TermGuard {
+    saved: Option<libc::termios>,
+}
+
+impl TermGuard {
+    fn new(silent: bool, char_mode: bool) -> TermGuard {
+        unsafe {
+            if (!silent && !char_mode) || libc::isatty(0) == 0 {
+                return TermGuard { saved: None };
+            }
+            let mut term: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(0, &mut term) != 0 {
+                return TermGuard { saved: None };
+            }
+            let saved = term;
+            if silent {
+                term.c_lflag &= !libc::ECHO;
+            }
+            if char_mode {
+                term.c_lflag &= !libc::ICANON;
+                term.c_cc[libc::VMIN] = 1;
+                term.c_cc[libc::VTIME] = 0;
+            }
+            libc::tcsetattr(0, libc::TCSANOW, &term);
+            TermGuard { saved: Some(saved) }
+        }
+    }
+}
+
+impl Drop for TermGuard {
+    fn drop(&mut self) {
+        if let Some(saved) = self.saved {
+            unsafe { libc::tcsetattr(0, libc::TCSANOW, &saved); }
+        }
+    }
+}
+
+/// Waits for one byte on stdin; reads the fd directly so no input is left
+/// in a buffer that child processes would never see
+fn read_byte(deadline: Option<Instant>) -> Result<u8, ReadError> {
+    if let Some(deadline) = deadline {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let mut fds = libc::pollfd { fd: 0, events: libc::POLLIN, revents: 0 };
+        let ready = unsafe { libc::poll(&mut fds, 1, left.as_millis() as libc::c_int) };
+        if ready == 0 || left.is_zero() {
+            return Err(ReadError::Timeout(String::new()));
+        }
+    }
+    let mut byte = 0u8;
+    let n = unsafe { libc::read(0, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+    if n == 1 { Ok(byte) } else { Err(ReadError::Eof) }
+}
+
+/// Bytes read so far and how many whole characters they make
+#[derive(Default)]
+struct Input {
+    bytes: Vec<u8>,
+    chars: usize,
+    //Bytes still missing from the character being read
+    missing: usize,
+}
+
+impl Input {
+    /// Counts a character once all of its bytes are there, so -n never cuts one in half
+    fn push(&mut self, byte: u8) {
+        self.bytes.push(byte);
+        if byte & 0xC0 == 0x80 && self.missing > 0 {
+            self.missing -= 1;
+        } else {
+            self.missing = match byte {
+                0xF0.. => 3,
+                0xE0.. => 2,
+                0xC0.. => 1,
+                _ => 0,
+            };
+        }
+        if self.missing == 0 {
+            self.chars += 1;
+        }
+    }
+}
+
+/// Reads one line (or max_chars characters) from stdin
+pub fn read_input(options: &ReadOptions) -> Result<String, ReadError> {
+    if !options.prompt.is_empty() {
+        eprint!("{}", options.prompt);
+        let _ = std::io::stderr().flush();
+    }
+
+    let guard = TermGuard::new(options.silent, options.max_chars.is_some());
+    let deadline = options.timeout.map(|t| Instant::now() + t);
+    let mut input = Input::default();
+    let mut escaped = false;
+
+    let result = loop {
+        if options.max_chars.is_some_and(|max| input.chars >= max) && input.missing == 0 {
+            break Ok(());
+        }
+        let byte = match read_byte(deadline) {
+            Ok(b) => b,
+            Err(ReadError::Eof) if !input.bytes.is_empty() => break Ok(()),
+            Err(ReadError::Timeout(_)) => break Err(ReadError::Timeout(String::new())),
+            Err(e) => break Err(e),
+        };
+
+        if escaped {
+            escaped = false;
+            // Backslash-newline continues the line
+            if byte != b'\n' {
+                input.push(byte);
+            }
+            continue;
+        }
+        if byte == b'\\' && !options.raw {
+            escaped = true;
+            continue;
+        }
+        if byte == b'\n' {
+            break Ok(());
+        }
+        input.push(byte);
+    };
+
+    // Enter was not echoed, move to the next line ourselves
+    if guard.saved.is_some() {
+        eprintln!();
+    }
+    let text = String::from_utf8_lossy(&input.bytes).into_owned();
+    match result {
+        Ok(()) => Ok(text),
+        Err(ReadError::Timeout(_)) => Err(ReadError::Timeout(text)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Splits by IFS; the last of `count` fields keeps the rest of the line
+fn split_fields(line: &str, count: usize) -> Vec<String> {
+    let ifs = env::var("IFS").unwrap_or_else(|_| " \t\n".to_string());
+    if ifs.is_empty() {
+        return vec![line.to_string()];
+    }
+    let is_sep = |c: char| ifs.contains(c);
+    let mut fields = Vec::new();
+    let mut rest = line.trim_matches(is_sep);
+
+    while !rest.is_empty() {
+        if fields.len() + 1 == count {
+            fields.push(rest.to_string());
+            break;
+        }
+        match rest.find(is_sep) {
+            Some(pos) => {
+                fields.push(rest[..pos].to_string());
+                rest = rest[pos..].trim_start_matches(is_sep);
+            }
+            None => {
+                fields.push(rest.to_string());
+                break;
+            }
+        }
+    }
+    fields
+}
+
+/// Read builtin: read [-r] [-s] [-p prompt] [-t timeout] [-n chars] [-a array] [var...]
+pub fn handle_read(args: Vec<String>) -> i32 {
+    let mut options = ReadOptions::default();
+    let mut array = None;
+    let mut iter = args.into_iter().skip(1).peekable();
+
+    // Options the getopts way: clustered (-rs), with the value attached (-t5) or next,
+    // up to -- or the first word that is not an option
+    while let Some(arg) = iter.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+        if arg == "--" {
+            break;
+        }
+        let mut flags = arg[1..].chars();
+        while let Some(flag) = flags.next() {
+            match flag {
+                'r' => options.raw = true,
+                's' => options.silent = true,
+                'p' | 't' | 'n' | 'a' => {
+                    let attached: String = flags.by_ref().collect();
+                    let value = if attached.is_empty() { iter.next() } else { Some(attached) };
+                    let Some(value) = value else {
+                        eprintln!("read: -{}: option requires an argument", flag);
+                        return 2;
+                    };
+                    match flag {
+                        'p' => options.prompt = value,
+                        'a' => array = Some(value),
+                        't' => match value.parse::<f64>() {
+                            Ok(secs) if secs >= 0.0 => options.timeout = Some(Duration::from_secs_f64(secs)),
+                            _ => {
+                                eprintln!("read: {}: invalid timeout", value);
+                                return 2;
+                            }
+                        },
+                        _ => match value.parse::<usize>() {
+                            Ok(n) => options.max_chars = Some(n),
+                            Err(_) => {
+                                eprintln!("read: {}: invalid number", value);
+                                return 2;
+                            }
+                        },
+                    }
+                }
+                _ => {
+                    eprintln!("read: -{}: invalid option", flag);
+                    eprintln!("read: usage: read [-r] [-s] [-p prompt] [-t timeout] [-n chars] [-a array] [name ...]");
+                    return 2;
+                }
+            }
+        }
+    }
+    let names: Vec<String> = iter.collect();
+    if let Some(name) = array.iter().chain(&names).find(|name| !var::is_valid_name(name)) {
+        eprintln!("read: '{}': not a valid identifier", name);
+        return 2;
+    }
+
+    // As in bash, what was typed before a timeout is still assigned
+    let (line, status) = match read_input(&options) {
+        Ok(line) => (line, 0),
+        Err(ReadError::Eof) => return 1,
+        // Same status as bash for a timeout
+        Err(ReadError::Timeout(partial)) => (partial, 142),
+    };
+
+    if let Some(array) = array {
+        var::set_array(&array, split_fields(&line, 0));
+        return status;
+    }
+    if names.is_empty() {
+        var::set("REPLY", &line);
+        return status;
+    }
+
+    let mut fields = split_fields(&line, names.len()).into_iter();
+    for name in &names {
+        var::set(name, &fields.next().unwrap_or_default());
+    }
+    status
+}
@@ -1,39 +1,80 @@
 // Copyright (C) <2026> <Bogdan Yachmenv>
 // SPDX-License-Identifier: GPL-3.0/
 
-use std::io;
 use std::io::Write;
 use std::env;
 use std::path::Path;
-use crate::GLOBAL_AST;
-use crate::GLOBAL_ENGINE;
+use super::{frecency, var};
 
 
 pub fn clr(){
     print!("\x1B[2J\x1B[H");
     std::io::stdout().flush().unwrap();
 }
-pub fn cd(args: Vec<String>) {
+/// Cd builtin: cd [directory | -]
+pub fn cd(args: Vec<String>) -> i32 {
+    let target = match args.get(1).map(|s| s.as_str()) {
+        Some("-") => match env::var("OLDPWD") {
+            Ok(old) => old,
+            Err(_) => {
+                eprintln!("cd: OLDPWD not set");
+                return 1;
+            }
+        },
+        Some(dir) => dir.to_string(),
+        None => env::var("HOME").unwrap_or_else(|_| "/".to_string()),
+    };
+    let (target, found_in_cdpath) = match search_cdpath(&target) {
+        Some(dir) => (dir, true),
+        None => (target, false),
+    };
+
+    if let Err(e) = change_dir(&target) {
+        eprintln!("cd: {}", e);
+        return 1;
+    }
+    // Like other shells, show where `cd -` or CDPATH went
+    if found_in_cdpath || args.get(1).is_some_and(|a| a == "-") {
+        println!("{}", env::var("PWD").unwrap_or_default());
+    }
+    0
+}
+
+/// Looks a relative directory up in the colon-separated CDPATH.
+/// Paths starting with /, . or .. and directories under the current one are never searched
+fn search_cdpath(target: &str) -> Option<String> {
+    if target.starts_with('/') || target == "." || target == ".."
+        || target.starts_with("./") || target.starts_with("../") || Path::new(target).is_dir() {
+        return None;
+    }
+    let cdpath = env::var("CDPATH").ok()?;
+    cdpath.split(':')
+        // An empty entry is the current directory, which plain cd already tries
+        .filter(|base| !base.is_empty() && *base != ".")
+        .map(|base| format!("{}/{}", base.trim_end_matches('/'), target))
+        .find(|dir| Path::new(dir).is_dir())
+}
+
+/// Changes the working directory, updates PWD/OLDPWD and runs the on_cd hook.
+/// Every directory change in the shell goes through here
+pub fn change_dir(target: &str) -> Result<(), String> {
     // Запоминаем старую директорию как строку
     let old_dir = env::current_dir()
         .map(|p| p.to_string_lossy().to_string())
         .unwrap_or_default();
 
-    let target = if args.len() >= 2 {
-        args[1].clone()
-    } else {
-        env::var("HOME").unwrap_or_else(|_| "/".to_string())
-    };
-
-    if let Err(e) = env::set_current_dir(Path::new(&target)) {
-        eprintln!("cd: {}: {}", target, e);
-        return;
+    if let Err(e) = env::set_current_dir(Path::new(target)) {
+        return Err(format!("{}: {}", target, e));
     }
 
     // Получаем новую директорию после смены
     let new_dir = env::current_dir()
         .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or(target);
+        .unwrap_or(target.to_string());
+
+    var::set("OLDPWD", &old_dir);
+    var::set("PWD", &new_dir);
+    frecency::add(&new_dir);
 
     // Используем глобальные движок и AST (thread-local)
     crate::GLOBAL_ENGINE.with(|eng| {
@@ -41,14 +82,14 @@ pub fn cd(args: Vec<String>) {
             if let Some(ast) = cell.borrow().as_ref() {
                 let engine = eng.borrow();
                 let mut scope = rhai::Scope::new();
-                if let Err(e) = engine.call_fn::<()>(&mut scope, ast, "on_cd", (old_dir, new_dir)) {
-                    if !e.to_string().contains("Function not found") {
-                        eprintln!("Error in on_cd hook: {}", e);
-                    }
+                if let Err(e) = engine.call_fn_with_options::<()>(crate::rhai_api::hook_options(), &mut scope, ast, "on_cd", (old_dir, new_dir))
+                    && !crate::rhai_api::is_missing_hook(&e, "on_cd") {
+                    eprintln!("Error in on_cd hook: {}", e);
                 }
             }
         });
     });
+    Ok(())
 }
 pub fn exit(args: Vec<String>){
     //Exit hook reslisation
@@ -57,15 +98,15 @@ pub fn exit(args: Vec<String>){
         if let Some(ast) = cell.borrow().as_ref() {
             let engine = eng.borrow();
             let mut scope = rhai::Scope::new();
-            if let Err(e) = engine.call_fn::<()>(&mut scope, ast, "on_exit", (args.clone())) {
-                if !e.to_string().contains("Function not found") {
-                        eprintln!("Error in on_cd hook: {}", e);
-                    }
+            if let Err(e) = engine.call_fn_with_options::<()>(crate::rhai_api::hook_options(), &mut scope, ast, "on_exit", args.clone())
+                && !crate::rhai_api::is_missing_hook(&e, "on_exit") {
+                    eprintln!("Error in on_exit hook: {}", e);
                 }
             }
         });
     });
     //Exit
     let code = args.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+    super::history::finish(code);
     std::process::exit(code);
 }
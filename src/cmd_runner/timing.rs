@@ -0,0 +1,132 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Duration, CPU time and status of the last command; named timers for Rhai
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use lazy_static::lazy_static;
+use libc::{c_int, pid_t};
+use super::var;
+
+#[derive(Clone, Default)]
+pub struct LastCommand {
+    pub command: String,
+    pub duration: Duration,
+    //User and system time of the programs it started
+    pub cpu: Duration,
+    pub status: i32,
+}
+
+/// A named timer: when it started and when lap() was last called
+struct Timer {
+    started: Instant,
+    lap: Instant,
+}
+
+lazy_static! {
+    static ref LAST: Mutex<LastCommand> = Mutex::new(LastCommand::default());
+    //Running timers by name, listed in name order
+    static ref TIMERS: Mutex<BTreeMap<String, Timer>> = Mutex::new(BTreeMap::new());
+}
+
+thread_local! {
+    //CPU time, in microseconds, of the children waited for on this thread while measure runs.
+    //Per thread so that prompt segment workers waiting for their commands are not counted
+    static MEASURED_CPU: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+fn micros(time: libc::timeval) -> u64 {
+    time.tv_sec as u64 * 1_000_000 + time.tv_usec as u64
+}
+
+/// waitpid that also counts the CPU time of the child for the command being measured
+pub fn wait_child(pid: pid_t, status: &mut c_int) -> pid_t {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    let ret = unsafe { libc::wait4(pid, status, 0, &mut usage) };
+    if ret > 0 {
+        let cpu = micros(usage.ru_utime) + micros(usage.ru_stime);
+        MEASURED_CPU.with(|m| m.set(m.get().map(|total| total + cpu)));
+    }
+    ret
+}
+
+/// Runs a command line with `run`, which returns its status, and records it as the last command.
+/// Sets $SBSH_CMD_DURATION to the duration in milliseconds
+pub fn measure(command: &str, run: impl FnOnce() -> i32) -> LastCommand {
+    MEASURED_CPU.with(|m| m.set(Some(0)));
+    let started = Instant::now();
+    let status = run();
+    let last = LastCommand {
+        command: command.to_string(),
+        duration: started.elapsed(),
+        cpu: Duration::from_micros(MEASURED_CPU.with(|m| m.take()).unwrap_or_default()),
+        status,
+    };
+    var::set("SBSH_CMD_DURATION", &last.duration.as_millis().to_string());
+    *LAST.lock().unwrap() = last.clone();
+    last
+}
+
+pub fn last() -> LastCommand {
+    LAST.lock().unwrap().clone()
+}
+
+/// Starts the timer, from zero if it was already running
+pub fn start_timer(name: &str) {
+    let now = Instant::now();
+    TIMERS.lock().unwrap().insert(name.to_string(), Timer { started: now, lap: now });
+}
+
+/// Time since the last lap (or the start), the timer keeps running
+pub fn lap_timer(name: &str) -> Option<Duration> {
+    let mut timers = TIMERS.lock().unwrap();
+    let timer = timers.get_mut(name)?;
+    let now = Instant::now();
+    let lap = now - timer.lap;
+    timer.lap = now;
+    Some(lap)
+}
+
+/// Removes the timer and returns the time since its start
+pub fn stop_timer(name: &str) -> Option<Duration> {
+    TIMERS.lock().unwrap().remove(name).map(|timer| timer.started.elapsed())
+}
+
+/// Sets a running timer back to zero
+pub fn reset_timer(name: &str) -> bool {
+    match TIMERS.lock().unwrap().get_mut(name) {
+        Some(timer) => {
+            timer.started = Instant::now();
+            timer.lap = timer.started;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Running timers and the time since their start
+pub fn timers() -> Vec<(String, Duration)> {
+    TIMERS.lock().unwrap().iter().map(|(name, timer)| (name.clone(), timer.started.elapsed())).collect()
+}
+
+/// "450ms", "3.2s", "1m 3.2s", "2h 5m 10s"
+pub fn format_duration(secs: f64) -> String {
+    if secs < 0.0 || !secs.is_finite() {
+        return format!("{}s", secs);
+    }
+    let ms = (secs * 1000.0).round() as u64;
+    if ms < 1000 {
+        return format!("{}ms", ms);
+    }
+    let tenths = (secs * 10.0).round() as u64;
+    if tenths < 600 {
+        return format!("{}.{}s", tenths / 10, tenths % 10);
+    }
+    if tenths < 36000 {
+        return format!("{}m {}.{}s", tenths / 600, tenths % 600 / 10, tenths % 10);
+    }
+    let total = secs.round() as u64;
+    format!("{}h {}m {}s", total / 3600, total % 3600 / 60, total % 60)
+}
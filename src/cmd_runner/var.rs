@@ -2,15 +2,44 @@
 // SPDX-License-Identifier: GPL-3.0/
 
 //Var realisation for SBSH
+use std::collections::HashMap;
 use std::env;
+use std::sync::Mutex;
+use lazy_static::lazy_static;
+use super::{dirs, options};
+
+lazy_static! {
+    //Arrays live in the shell only, the environment can't hold them
+    static ref ARRAYS: Mutex<HashMap<String, Vec<String>>> = Mutex::new(HashMap::new());
+}
+
+pub fn set_array(name: &str, values: Vec<String>) {
+    ARRAYS.lock().unwrap().insert(name.to_string(), values);
+}
+
+pub fn get_array(name: &str) -> Option<Vec<String>> {
+    ARRAYS.lock().unwrap().get(name).cloned()
+}
+
+/// True for a name a variable can have: [A-Za-z_][A-Za-z0-9_]*
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Sets an environment variable from shell code
+pub fn set(key: &str, value: &str) {
+    unsafe { set_var_unsafe(key, value); }
+}
 
 
 unsafe fn set_var_unsafe(key: &str, value: &str) {
-    env::set_var(key, value);
+    unsafe { env::set_var(key, value); }
 }
 
 unsafe fn remove_var_unsafe(key: &str) {
-    env::remove_var(key);
+    unsafe { env::remove_var(key); }
 }
 
 /// Var builtin 
@@ -41,6 +70,7 @@ pub fn handle_var(args: Vec<String>) {
             unsafe {
                 remove_var_unsafe(&args[2]);
             }
+            ARRAYS.lock().unwrap().remove(&args[2]);
         }
         _ => {
             if args.len() >= 3 && args[2] == "=" {
@@ -88,7 +118,7 @@ unsafe fn var_match(tokens: &[String]) {
         _ => unreachable!(),
     };
 
-    set_var_unsafe(&var_name, &result.to_string());
+    unsafe { set_var_unsafe(&var_name, &result.to_string()); }
 }
 
 ///Tokenesator
@@ -174,4 +204,145 @@ fn get_value(token: &str) -> f64 {
         }),
         Err(_) => panic!("Number Invalid: unknown variable '{}'", token),
     }
+}
+
+///Variable and tilde expansion of a raw command line ($NAME, ${NAME}, $?, ${name[i]}, ${name[@]}, ${#name[@]}, ~, ~N, ~user)
+///Single-quoted text is left alone, expanded values are quoted so shlex keeps them as one word
+pub fn expand(line: &str) -> Result<String, String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = String::with_capacity(line.len());
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if !in_single => {
+                out.push(c);
+                if let Some(next) = chars.get(i + 1) {
+                    out.push(*next);
+                    i += 1;
+                }
+            }
+            '\'' if !in_double => {
+                in_single = !in_single;
+                out.push(c);
+            }
+            '"' if !in_single => {
+                in_double = !in_double;
+                out.push(c);
+            }
+            // Tilde at the start of an unquoted word
+            '~' if !in_single && !in_double && (i == 0 || chars[i - 1].is_whitespace()) => {
+                let len = chars[i..].iter()
+                    .take_while(|c| **c != '/' && !c.is_whitespace() && **c != '"' && **c != '\'')
+                    .count();
+                let prefix: String = chars[i..i + len].iter().collect();
+                match dirs::expand_tilde(&prefix) {
+                    Some(path) => {
+                        out.push_str(&quote_value(&path, false));
+                        i += len - 1;
+                    }
+                    None => out.push(c),
+                }
+            }
+            '$' if !in_single => {
+                let (name, len) = parse_name(&chars[i + 1..]);
+                if name.is_empty() {
+                    out.push(c);
+                } else {
+                    let words = lookup(&name)?;
+                    if in_double && name.ends_with("[@]") {
+                        // "${name[@]}" gives one word per element: close and reopen the quotes between them
+                        let quoted: Vec<String> = words.iter().map(|w| quote_value(w, true)).collect();
+                        out.push_str(&quoted.join("\" \""));
+                    } else if in_double {
+                        out.push_str(&quote_value(&words.join(" "), true));
+                    } else {
+                        // Unquoted, an empty value is no word at all
+                        let quoted: Vec<String> = words.iter().filter(|w| !w.is_empty()).map(|w| quote_value(w, false)).collect();
+                        out.push_str(&quoted.join(" "));
+                    }
+                    i += len;
+                }
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+    Ok(out)
+}
+
+///Returns the variable name after '$' and how many chars it takes
+fn parse_name(rest: &[char]) -> (String, usize) {
+    match rest.first() {
+        Some('?') => ("?".to_string(), 1),
+        Some('{') => match rest.iter().position(|c| *c == '}') {
+            Some(end) => (rest[1..end].iter().collect(), end + 1),
+            None => (String::new(), 0),
+        },
+        Some(c) if c.is_ascii_alphabetic() || *c == '_' => {
+            let name: String = rest.iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                .collect();
+            let len = name.len();
+            (name, len)
+        }
+        _ => (String::new(), 0),
+    }
+}
+
+///Value of a parameter, arrays expanded with [@] give one word per element
+fn lookup(name: &str) -> Result<Vec<String>, String> {
+    let unbound = || if options::get("nounset") {
+        Err(format!("{}: unbound variable", name))
+    } else {
+        Ok(vec![String::new()])
+    };
+
+    if name == "?" {
+        return Ok(vec![crate::cmd_runner::last_status().to_string()]);
+    }
+
+    if let Some((array, index)) = name.split_once('[') {
+        let index = index.trim_end_matches(']');
+        let (count, array) = match array.strip_prefix('#') {
+            Some(array) => (true, array),
+            None => (false, array),
+        };
+        let Some(values) = get_array(array) else {
+            return unbound();
+        };
+        if count {
+            return Ok(vec![values.len().to_string()]);
+        }
+        if index == "@" || index == "*" {
+            return Ok(values);
+        }
+        return match index.parse::<usize>().ok().and_then(|i| values.get(i)) {
+            Some(value) => Ok(vec![value.clone()]),
+            None => unbound(),
+        };
+    }
+
+    match env::var(name) {
+        Ok(val) => Ok(vec![val]),
+        Err(_) => unbound(),
+    }
+}
+
+fn quote_value(value: &str, in_double: bool) -> String {
+    if in_double {
+        let mut out = String::with_capacity(value.len());
+        for c in value.chars() {
+            if matches!(c, '"' | '\\' | '$' | '`') {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out
+    } else {
+        shlex::try_quote(value).map(|q| q.into_owned()).unwrap_or_default()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,61 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+///Line editor integration: rustyline helper for the input line
+use rustyline::completion::{Completer, Pair};
+use std::borrow::Cow;
+use rustyline::highlight::{CmdKind, Highlighter};
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+pub mod completion;
+pub mod fuzzy;
+pub mod highlight;
+pub mod keys;
+pub mod prompt;
+pub mod segments;
+pub mod suggest;
+
+pub struct ShellHelper;
+
+impl Helper for ShellHelper {}
+impl Validator for ShellHelper {}
+impl Hinter for ShellHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        let hint = suggest::hint(line, pos);
+        prompt::set_text(line, pos, hint.as_deref().unwrap_or_default());
+        hint
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        Ok(completion::complete(&line[..pos]))
+    }
+}
+
+impl Highlighter for ShellHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        Cow::Owned(highlight::highlight(line))
+    }
+
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, _default: bool) -> Cow<'b, str> {
+        match prompt::styled(prompt) {
+            Some(styled) => Cow::Owned(styled),
+            None => Cow::Borrowed(prompt),
+        }
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(highlight::colored(hint, "suggestion"))
+    }
+
+    // Redraw on every edit, a command turns from red to green while it is typed
+    fn highlight_char(&self, _line: &str, _pos: usize, kind: CmdKind) -> bool {
+        kind != CmdKind::MoveCursor
+    }
+}
@@ -0,0 +1,316 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Tab completion: commands, paths, variables, users and completers registered from Rhai
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::ffi::CStr;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use rhai::{Array, Dynamic, FnPtr, INT, AST};
+use rustyline::completion::Pair;
+use crate::cmd_runner::{self, aliases, dirs};
+
+thread_local! {
+    //Completers registered with complete("cmd", |words, cursor| ...)
+    static COMPLETERS: RefCell<HashMap<String, FnPtr>> = RefCell::new(HashMap::new());
+    //Results of complete_cache(key, seconds, || ...)
+    static CACHE: RefCell<HashMap<String, (Instant, Array)>> = RefCell::new(HashMap::new());
+}
+
+/// A word of the line being edited, unescaped
+pub struct Word {
+    pub start: usize,
+    pub text: String,
+    /// Quote the word was opened with, if it is still open
+    pub quote: Option<char>,
+}
+
+/// Splits the text before the cursor like the shell would and returns the words
+/// of the last command; the last word is the one under the cursor (maybe empty)
+pub fn command_words(line: &str) -> Vec<Word> {
+    let mut words: Vec<Word> = Vec::new();
+    let mut in_word = false;
+    let mut escaped = false;
+    let mut quote = None;
+
+    for (i, c) in line.char_indices() {
+        if !in_word && !(quote.is_none() && (c.is_whitespace() || c == '|' || c == ';')) {
+            words.push(Word { start: i, text: String::new(), quote: None });
+            in_word = true;
+        }
+        if escaped {
+            escaped = false;
+            words.last_mut().unwrap().text.push(c);
+            continue;
+        }
+        match (c, quote) {
+            ('\\', None) | ('\\', Some('"')) => escaped = true,
+            ('\'', None) | ('"', None) => quote = Some(c),
+            (q, Some(open)) if q == open => quote = None,
+            (c, None) if c.is_whitespace() || c == '|' || c == ';' => {
+                in_word = false;
+                if c == '|' || c == ';' {
+                    words.clear();
+                }
+            }
+            (c, _) => words.last_mut().unwrap().text.push(c),
+        }
+    }
+    if !in_word {
+        words.push(Word { start: line.len(), text: String::new(), quote: None });
+    }
+    words.last_mut().unwrap().quote = quote;
+    words
+}
+
+/// Completion for the text before the cursor: start of the replaced word and candidates
+pub fn complete(line: &str) -> (usize, Vec<Pair>) {
+    // An abbreviation in command position completes to its expansion
+    if let Some((start, expansion)) = aliases::abbreviation_at(line, line.len()) {
+        return (start, vec![Pair { display: expansion.clone(), replacement: expansion + " " }]);
+    }
+    let words = command_words(line);
+    let word = words.last().unwrap();
+    let text = &word.text;
+
+    // Text of the word as typed, with its quotes
+    let typed = &line[word.start..];
+    let mut start = word.start;
+
+    let candidates = if text.starts_with('$') && !text.contains('/') && word.quote != Some('\'') {
+        // Replace from the $ so that an opening quote stays, and put back a closing one
+        start += typed.find('$').unwrap_or(0);
+        let closing = match typed.chars().last() {
+            Some(q) if word.quote.is_none() && (q == '"' || q == '\'') && typed.starts_with(q) => q.to_string(),
+            _ => String::new(),
+        };
+        complete_variables(text).into_iter()
+            .map(|pair| Pair { replacement: pair.replacement + closing.as_str(), ..pair })
+            .collect()
+    } else if text.starts_with('~') && !text.contains('/') && typed.starts_with('~') {
+        complete_users(text)
+    } else if words.len() == 1 && !text.contains('/') {
+        complete_commands(text, word.quote)
+    } else if let Some(list) = call_completer(&words) {
+        list.into_iter()
+            .filter(|c| c.starts_with(text.as_str()))
+            .map(|c| Pair { display: c.clone(), replacement: finish(&c, word.quote) })
+            .collect()
+    } else {
+        complete_paths(text, word.quote, false)
+    };
+    (start, candidates)
+}
+
+/// Escapes characters the shell would split or expand; a leading ~ or $VAR
+/// path segment is kept as it is so it still expands
+pub fn escape(text: &str, quote: Option<char>) -> String {
+    let (kept, rest) = match text.split_once('/') {
+        Some((first, _)) if first.starts_with('~') || first.starts_with('$') => text.split_at(first.len()),
+        _ => ("", text),
+    };
+    match quote {
+        Some(q) => format!("{}{}{}", q, kept, rest),
+        None => {
+            let mut out = String::from(kept);
+            for (i, c) in rest.chars().enumerate() {
+                if c.is_whitespace() || "'\"\\$&|;<>()*?[]#!{}`".contains(c) || (c == '~' && i == 0) {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            out
+        }
+    }
+}
+
+/// Replacement for a candidate: escaped, and closed with a quote and space unless it
+/// is a directory or an option waiting for its value
+fn finish(candidate: &str, quote: Option<char>) -> String {
+    let escaped = escape(candidate, quote);
+    if candidate.ends_with('/') || candidate.ends_with('=') {
+        escaped
+    } else {
+        format!("{}{} ", escaped, quote.map(String::from).unwrap_or_default())
+    }
+}
+
+/// Builtins, aliases and programs from PATH
+fn complete_commands(prefix: &str, quote: Option<char>) -> Vec<Pair> {
+    let mut names: BTreeSet<String> = cmd_runner::BUILTINS.iter()
+        .map(|s| s.to_string())
+        .chain(aliases::list().into_iter().map(|(name, _)| name))
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+
+    for dir in env::var("PATH").unwrap_or_default().split(':').filter(|d| !d.is_empty()) {
+        let Ok(entries) = fs::read_dir(dir) else { continue };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(prefix)
+                && entry.path().metadata().is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0) {
+                names.insert(name);
+            }
+        }
+    }
+
+    let mut candidates: Vec<Pair> = names.into_iter()
+        .map(|name| Pair { display: name.clone(), replacement: finish(&name, quote) })
+        .collect();
+    // Directories too, for autocd and ./scripts
+    if candidates.is_empty() || prefix.starts_with('.') {
+        candidates.extend(complete_paths(prefix, quote, true));
+    }
+    candidates
+}
+
+/// Files and directories starting with `word`, unescaped; directories end with '/'.
+/// A leading ~ or $VAR segment is looked up on disk but kept in the result
+pub fn list_paths(word: &str, dirs_only: bool) -> Vec<String> {
+    let (dir_part, file_prefix) = match word.rfind('/') {
+        Some(pos) => (&word[..=pos], &word[pos + 1..]),
+        None => ("", word),
+    };
+    let (kept, rest) = match dir_part.split_once('/') {
+        Some((first, rest)) if first.starts_with('~') || first.starts_with('$') => (first, rest),
+        _ => ("", dir_part),
+    };
+    let lookup_dir = if dir_part.is_empty() {
+        ".".to_string()
+    } else if kept.starts_with('~') {
+        match dirs::expand_tilde(kept) {
+            Some(home) => format!("{}/{}", home, rest),
+            None => dir_part.to_string(),
+        }
+    } else if let Some(name) = kept.strip_prefix('$') {
+        let name = name.trim_start_matches('{').trim_end_matches('}');
+        format!("{}/{}", env::var(name).unwrap_or_default(), rest)
+    } else {
+        dir_part.to_string()
+    };
+
+    let Ok(entries) = fs::read_dir(&lookup_dir) else {
+        return Vec::new();
+    };
+    let mut found: Vec<String> = entries.flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Hidden files only when asked for
+            if !name.starts_with(file_prefix) || (name.starts_with('.') && !file_prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = Path::new(&lookup_dir).join(&name).is_dir();
+            if dirs_only && !is_dir {
+                return None;
+            }
+            Some(format!("{}{}{}", dir_part, name, if is_dir { "/" } else { "" }))
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+pub fn complete_paths(word: &str, quote: Option<char>, dirs_only: bool) -> Vec<Pair> {
+    list_paths(word, dirs_only).into_iter()
+        .map(|path| {
+            let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+            let display = if path.ends_with('/') { format!("{}/", name) } else { name.to_string() };
+            Pair { display, replacement: finish(&path, quote) }
+        })
+        .collect()
+}
+
+fn complete_variables(word: &str) -> Vec<Pair> {
+    let braced = word.starts_with("${");
+    let prefix = word.trim_start_matches('$').trim_start_matches('{');
+    let names: BTreeSet<String> = env::vars()
+        .map(|(name, _)| name)
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    names.into_iter()
+        .map(|name| {
+            let replacement = if braced { format!("${{{}}}", name) } else { format!("${}", name) };
+            Pair { display: name, replacement }
+        })
+        .collect()
+}
+
+/// ~user names from the password database
+fn complete_users(word: &str) -> Vec<Pair> {
+    let prefix = &word[1..];
+    let mut names = BTreeSet::new();
+    unsafe {
+        libc::setpwent();
+        loop {
+            let pw = libc::getpwent();
+            if pw.is_null() {
+                break;
+            }
+            let name = CStr::from_ptr((*pw).pw_name).to_string_lossy().into_owned();
+            if name.starts_with(prefix) {
+                names.insert(format!("~{}", name));
+            }
+        }
+        libc::endpwent();
+    }
+    names.into_iter()
+        .map(|name| Pair { display: name.clone(), replacement: format!("{}/", name) })
+        .collect()
+}
+
+pub fn register(command: String, callback: FnPtr) {
+    COMPLETERS.with(|c| c.borrow_mut().insert(command, callback));
+}
+
+/// Runs the Rhai completer for the command, None if there is none or it returned ()
+fn call_completer(words: &[Word]) -> Option<Vec<String>> {
+    let mut command = words[0].text.clone();
+    // An alias completes like the command it starts with
+    if let Some(alias) = aliases::get(&command)
+        && let Some(first) = alias.split_whitespace().next() {
+        command = first.to_string();
+    }
+    let callback = COMPLETERS.with(|c| c.borrow().get(&command).cloned())?;
+
+    let args: Array = words.iter().map(|w| Dynamic::from(w.text.clone())).collect();
+    let cursor = (words.len() - 1) as INT;
+    let result = crate::GLOBAL_ENGINE.with(|eng| {
+        crate::GLOBAL_AST.with(|cell| {
+            let empty = AST::empty();
+            let ast = cell.borrow();
+            callback.call::<Dynamic>(&eng.borrow(), ast.as_ref().unwrap_or(&empty), (args, cursor))
+        })
+    });
+
+    match result {
+        Ok(value) if value.is_unit() => None,
+        Ok(value) => match value.try_cast::<Array>() {
+            Some(list) => Some(list.into_iter().map(|v| v.to_string()).collect()),
+            None => {
+                eprintln!("\nCompleter for {} must return an array", command);
+                None
+            }
+        },
+        Err(e) => {
+            eprintln!("\nError in completer for {}: {}", command, e);
+            None
+        }
+    }
+}
+
+/// Cached value for complete_cache if it is younger than `max_age`
+pub fn cached(key: &str, max_age: Duration) -> Option<Array> {
+    CACHE.with(|c| {
+        c.borrow().get(key)
+            .filter(|(time, _)| time.elapsed() < max_age)
+            .map(|(_, value)| value.clone())
+    })
+}
+
+pub fn store(key: String, value: Array) {
+    CACHE.with(|c| c.borrow_mut().insert(key, (Instant::now(), value)));
+}
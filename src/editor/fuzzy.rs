@@ -0,0 +1,312 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Full-screen fuzzy finder for history, files and directories
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::time::Duration;
+use rustyline::{Cmd, ConditionalEventHandler, Event, EventContext, Movement, RepeatCount};
+use crate::cmd_runner::{frecency, history};
+use super::completion;
+
+/// Files listed by Ctrl-T at most
+const MAX_FILES: usize = 50000;
+
+/// Puts the terminal into raw mode and the alternate screen while alive
+struct Screen {
+    saved: Option<libc::termios>,
+}
+
+impl Screen {
+    fn open() -> Option<Screen> {
+        unsafe {
+            if libc::isatty(0) == 0 || libc::isatty(2) == 0 {
+                return None;
+            }
+            let mut term: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(0, &mut term) != 0 {
+                return None;
+            }
+            let saved = term;
+            term.c_lflag &= !(libc::ECHO | libc::ICANON | libc::ISIG | libc::IEXTEN);
+            term.c_iflag &= !(libc::ICRNL | libc::IXON);
+            term.c_cc[libc::VMIN] = 1;
+            term.c_cc[libc::VTIME] = 0;
+            libc::tcsetattr(0, libc::TCSANOW, &term);
+            eprint!("\x1b[?1049h\x1b[?25l");
+            Some(Screen { saved: Some(saved) })
+        }
+    }
+
+    fn size() -> (usize, usize) {
+        unsafe {
+            let mut ws: libc::winsize = std::mem::zeroed();
+            if libc::ioctl(2, libc::TIOCGWINSZ, &mut ws) == 0 && ws.ws_row > 0 {
+                (ws.ws_row as usize, ws.ws_col as usize)
+            } else {
+                (24, 80)
+            }
+        }
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        eprint!("\x1b[?25h\x1b[?1049l");
+        let _ = std::io::stderr().flush();
+        if let Some(saved) = self.saved {
+            unsafe { libc::tcsetattr(0, libc::TCSANOW, &saved); }
+        }
+    }
+}
+
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Up,
+    Down,
+    Cancel,
+    ClearQuery,
+    Other,
+}
+
+fn read_byte(timeout: Option<Duration>) -> Option<u8> {
+    if let Some(timeout) = timeout {
+        let mut fds = libc::pollfd { fd: 0, events: libc::POLLIN, revents: 0 };
+        if unsafe { libc::poll(&mut fds, 1, timeout.as_millis() as libc::c_int) } <= 0 {
+            return None;
+        }
+    }
+    let mut byte = 0u8;
+    let n = unsafe { libc::read(0, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+    (n == 1).then_some(byte)
+}
+
+fn read_key() -> Key {
+    let Some(byte) = read_byte(None) else {
+        return Key::Cancel;
+    };
+    match byte {
+        b'\r' => Key::Enter,
+        0x7f | 0x08 => Key::Backspace,
+        0x03 | 0x07 | 0x04 => Key::Cancel,
+        0x15 => Key::ClearQuery,
+        0x10 | 0x0b => Key::Up,
+        0x0e | 0x0a => Key::Down,
+        0x1b => {
+            // A lone Esc cancels, arrows come as Esc [ A / Esc O A
+            match read_byte(Some(Duration::from_millis(30))) {
+                None => Key::Cancel,
+                Some(b'[') | Some(b'O') => match read_byte(Some(Duration::from_millis(30))) {
+                    Some(b'A') => Key::Up,
+                    Some(b'B') => Key::Down,
+                    _ => Key::Other,
+                },
+                Some(_) => Key::Other,
+            }
+        }
+        b if b < 0x20 => Key::Other,
+        b => {
+            // Collect the rest of a UTF-8 character
+            let len = match b {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            let mut bytes = vec![b];
+            for _ in 1..len {
+                bytes.extend(read_byte(None));
+            }
+            String::from_utf8(bytes).ok()
+                .and_then(|s| s.chars().next())
+                .map(Key::Char)
+                .unwrap_or(Key::Other)
+        }
+    }
+}
+
+/// Score of `item` for `query` and the positions of matched characters, None if it does not match.
+/// Characters must appear in order; consecutive matches and matches at word starts score higher.
+/// Case-insensitive unless the query has capitals
+pub fn fuzzy_match(query: &str, item: &str) -> Option<(i64, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    let smart_case = query.chars().any(|c| c.is_uppercase());
+    let same = |a: char, b: char| if smart_case { a == b } else { a.to_lowercase().eq(b.to_lowercase()) };
+    let chars: Vec<char> = item.chars().collect();
+    let mut positions = Vec::new();
+    let mut score = 0i64;
+    let mut next = 0;
+
+    for q in query.chars() {
+        let found = (next..chars.len()).find(|&i| same(chars[i], q))?;
+        score += 10;
+        if positions.last().is_some_and(|&last| last + 1 == found) {
+            score += 15;
+        }
+        if found == 0 || !chars[found - 1].is_alphanumeric() {
+            score += 10;
+        }
+        score -= (found - next) as i64;
+        positions.push(found);
+        next = found + 1;
+    }
+    // Shorter items win ties
+    score -= chars.len() as i64 / 10;
+    Some((score, positions))
+}
+
+fn draw(prompt: &str, query: &str, items: &[String], matches: &[(usize, Vec<usize>)], selected: usize, offset: usize) {
+    let (rows, cols) = Screen::size();
+    let mut out = String::from("\x1b[H\x1b[2J");
+    out.push_str(&format!("{}{}\x1b[7m \x1b[0m\r\n", prompt, query));
+    out.push_str(&format!("\x1b[2m  {}/{}\x1b[0m\r\n", matches.len(), items.len()));
+
+    for (row, (index, positions)) in matches.iter().enumerate().skip(offset).take(rows.saturating_sub(2)) {
+        let is_selected = row == selected;
+        out.push_str(if is_selected { "\x1b[1m> " } else { "  " });
+        for (i, c) in items[*index].chars().take(cols.saturating_sub(2)).enumerate() {
+            if c.is_control() {
+                out.push(' ');
+            } else if positions.contains(&i) {
+                out.push_str(&format!("\x1b[32m{}\x1b[39m", c));
+            } else {
+                out.push(c);
+            }
+        }
+        out.push_str("\x1b[0m\r\n");
+    }
+    eprint!("{}", out.trim_end_matches("\r\n"));
+    let _ = std::io::stderr().flush();
+}
+
+/// Shows the finder over `items` and returns the chosen one, None if cancelled
+pub fn select(items: &[String], prompt: &str) -> Option<String> {
+    let _screen = Screen::open()?;
+    let mut query = String::new();
+    let mut selected = 0;
+    let mut offset = 0;
+
+    loop {
+        let mut matches: Vec<(usize, i64, Vec<usize>)> = items.iter()
+            .enumerate()
+            .filter_map(|(i, item)| fuzzy_match(&query, item).map(|(score, pos)| (i, score, pos)))
+            .collect();
+        // Stable sort keeps the order of equal scores (newest history first)
+        matches.sort_by_key(|(_, score, _)| -score);
+        let matches: Vec<(usize, Vec<usize>)> = matches.into_iter().map(|(i, _, pos)| (i, pos)).collect();
+
+        selected = selected.min(matches.len().saturating_sub(1));
+        let visible = Screen::size().0.saturating_sub(2).max(1);
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + visible {
+            offset = selected + 1 - visible;
+        }
+        draw(prompt, &query, items, &matches, selected, offset);
+
+        match read_key() {
+            Key::Char(c) => {
+                query.push(c);
+                selected = 0;
+                offset = 0;
+            }
+            Key::Backspace => {
+                query.pop();
+                selected = 0;
+                offset = 0;
+            }
+            Key::ClearQuery => query.clear(),
+            Key::Up => selected = selected.saturating_sub(1),
+            Key::Down => selected += 1,
+            Key::Enter => return matches.get(selected).map(|(i, _)| items[*i].clone()),
+            Key::Cancel => return None,
+            Key::Other => {}
+        }
+    }
+}
+
+/// History, newest first, without repeats
+pub fn history_items() -> Vec<String> {
+    let mut seen = HashSet::new();
+    history::with_entries(|entries| {
+        entries.iter().rev()
+            .filter(|e| seen.insert(e.command.clone()))
+            .map(|e| e.command.clone())
+            .collect()
+    })
+}
+
+/// Files and directories under the current directory, hidden ones skipped
+pub fn file_items() -> Vec<String> {
+    let mut found = Vec::new();
+    let mut dirs = vec![String::new()];
+    'walk: while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(if dir.is_empty() { "." } else { &dir }) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            let path = format!("{}{}", dir, name);
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                found.push(format!("{}/", path));
+                dirs.push(format!("{}/", path));
+            } else {
+                found.push(path);
+            }
+            if found.len() >= MAX_FILES {
+                break 'walk;
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Directories from the frecency database, best first
+pub fn dir_items() -> Vec<String> {
+    frecency::query(&[]).into_iter().map(|(path, _)| path).collect()
+}
+
+/// Ctrl-R: replaces the line with a command from history
+pub struct HistorySearch;
+
+impl ConditionalEventHandler for HistorySearch {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _positive: bool, _ctx: &EventContext) -> Option<Cmd> {
+        match select(&history_items(), "history> ") {
+            Some(command) => Some(Cmd::Replace(Movement::WholeLine, Some(command))),
+            None => Some(Cmd::Repaint),
+        }
+    }
+}
+
+/// Ctrl-T: inserts a file path at the cursor
+pub struct FileSearch;
+
+impl ConditionalEventHandler for FileSearch {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _positive: bool, _ctx: &EventContext) -> Option<Cmd> {
+        match select(&file_items(), "files> ") {
+            Some(path) => Some(Cmd::Insert(1, completion::escape(&path, None))),
+            None => Some(Cmd::Repaint),
+        }
+    }
+}
+
+/// Alt-C: replaces the line with `cd` to a frequently used directory
+pub struct DirSearch;
+
+impl ConditionalEventHandler for DirSearch {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _positive: bool, _ctx: &EventContext) -> Option<Cmd> {
+        match select(&dir_items(), "dirs> ") {
+            Some(dir) => Some(Cmd::Replace(Movement::WholeLine, Some(format!("cd {}", completion::escape(&dir, None))))),
+            None => Some(Cmd::Repaint),
+        }
+    }
+}
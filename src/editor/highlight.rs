@@ -0,0 +1,214 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Syntax highlighting of the input line
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use lazy_static::lazy_static;
+use crate::api;
+use crate::cmd_runner::{self, aliases, options, pipeline};
+
+/// Kinds of highlighted text and their default colors
+const DEFAULT_COLORS: &[(&str, [u8; 3])] = &[
+    ("command", [80, 200, 120]),
+    ("unknown", [230, 80, 80]),
+    ("string", [220, 180, 90]),
+    ("variable", [100, 180, 240]),
+    ("operator", [200, 120, 220]),
+    ("comment", [128, 128, 128]),
+    ("suggestion", [110, 110, 110]),
+];
+
+lazy_static! {
+    static ref COLORS: Mutex<HashMap<String, [u8; 3]>> = Mutex::new(
+        DEFAULT_COLORS.iter().map(|(kind, rgb)| (kind.to_string(), *rgb)).collect()
+    );
+}
+
+/// Returns false if the kind is unknown
+pub fn set_color(kind: &str, rgb: [u8; 3]) -> bool {
+    match COLORS.lock().unwrap().get_mut(kind) {
+        Some(color) => {
+            *color = rgb;
+            true
+        }
+        None => false,
+    }
+}
+
+/// `text` in the color of `kind`
+pub fn colored(text: &str, kind: &str) -> String {
+    match COLORS.lock().unwrap().get(kind) {
+        Some(&[r, g, b]) => api::set_color(text.to_string(), r as i64, g as i64, b as i64),
+        None => text.to_string(),
+    }
+}
+
+fn paint(out: &mut String, text: &str, kind: &str) {
+    if !text.is_empty() {
+        out.push_str(&colored(text, kind));
+    }
+}
+
+/// True if running `name` would not end with "command not found"
+fn is_valid_command(name: &str) -> bool {
+    cmd_runner::is_builtin(name)
+        || aliases::get(name).is_some()
+        || aliases::get_global(name).is_some()
+        || aliases::abbreviation(name).is_some()
+        || cmd_runner::find_executable(name).is_some()
+        || (options::get("autocd") && Path::new(name).is_dir())
+}
+
+/// Splits the line into words the way the shell does: on whitespace outside of quotes
+fn split_words(line: &str) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        if start.is_none() {
+            if c.is_whitespace() {
+                continue;
+            }
+            start = Some(i);
+        }
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (c, quote) {
+            ('\\', q) if q != Some('\'') => escaped = true,
+            ('\'', None) | ('"', None) => quote = Some(c),
+            (q, Some(open)) if q == open => quote = None,
+            (c, None) if c.is_whitespace() => {
+                words.push((start.take().unwrap(), i));
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push((s, line.len()));
+    }
+    words
+}
+
+/// Length of a variable reference at the start of `text` ($NAME, ${...}, $?), 0 if none
+fn variable_len(text: &str) -> usize {
+    let rest = &text[1..];
+    if rest.starts_with('{') {
+        return rest.find('}').map(|end| end + 2).unwrap_or(text.len());
+    }
+    if rest.starts_with('?') {
+        return 2;
+    }
+    let name = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+    if name == 0 { 0 } else { name + 1 }
+}
+
+/// Colors the quoted strings and variables inside one word
+fn paint_word(out: &mut String, word: &str, default: &str) {
+    let mut plain = String::new();
+    let mut rest = word;
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '\\' => rest.chars().nth(1).map(|n| 1 + n.len_utf8()).unwrap_or(1),
+            '\'' | '"' => {
+                let end = rest[1..].find(c).map(|e| e + 2).unwrap_or(rest.len());
+                paint(out, &std::mem::take(&mut plain), default);
+                if c == '"' {
+                    paint_double_quoted(out, &rest[..end]);
+                } else {
+                    paint(out, &rest[..end], "string");
+                }
+                rest = &rest[end..];
+                continue;
+            }
+            '$' if variable_len(rest) > 0 => {
+                let end = variable_len(rest);
+                paint(out, &std::mem::take(&mut plain), default);
+                paint(out, &rest[..end], "variable");
+                rest = &rest[end..];
+                continue;
+            }
+            _ => c.len_utf8(),
+        };
+        plain.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    paint(out, &plain, default);
+}
+
+/// Double quoted strings still expand variables, so those keep their own color
+fn paint_double_quoted(out: &mut String, text: &str) {
+    let mut start = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let c = text[i..].chars().next().unwrap();
+        if c == '\\' {
+            i += 1 + text[i + 1..].chars().next().map(char::len_utf8).unwrap_or(0);
+            continue;
+        }
+        if c == '$' && variable_len(&text[i..]) > 0 {
+            let len = variable_len(&text[i..]);
+            paint(out, &text[start..i], "string");
+            paint(out, &text[i..i + len], "variable");
+            i += len;
+            start = i;
+            continue;
+        }
+        i += c.len_utf8();
+    }
+    paint(out, &text[start..], "string");
+}
+
+/// The line with ANSI colors; the visible text is unchanged
+pub fn highlight(line: &str) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+    let mut command_next = true;
+    // A bare redirection operator is followed by a file name, not a command
+    let mut target_next = false;
+
+    for (start, end) in split_words(line) {
+        out.push_str(&line[last..start]);
+        last = end;
+        let word = &line[start..end];
+
+        if word.starts_with('#') {
+            paint(&mut out, &line[start..], "comment");
+            return out;
+        }
+        if pipeline::is_operator(word) {
+            let op_len = pipeline::OPERATORS.iter()
+                .find(|op| word.starts_with(**op))
+                .map(|op| op.len())
+                .unwrap_or(word.len());
+            paint(&mut out, &word[..op_len], "operator");
+            paint_word(&mut out, &word[op_len..], "");
+            command_next = word == "|";
+            target_next = word != "|" && op_len == word.len() && word != "2>&1";
+            continue;
+        }
+
+        if command_next && !target_next {
+            command_next = false;
+            let is_literal = !word.contains(['\'', '"', '$', '\\']);
+            let kind = if !is_literal {
+                ""
+            } else if is_valid_command(word) {
+                "command"
+            } else {
+                "unknown"
+            };
+            paint_word(&mut out, word, kind);
+        } else {
+            paint_word(&mut out, word, "");
+        }
+        target_next = false;
+    }
+    out.push_str(&line[last..]);
+    out
+}
@@ -0,0 +1,193 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Editor settings and key bindings from Rhai
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use lazy_static::lazy_static;
+use rhai::{Dynamic, FnPtr, AST};
+use rustyline::config::{BellStyle, CompletionType, EditMode};
+use rustyline::{Cmd, ConditionalEventHandler, Config, Event, EventContext, KeyCode, KeyEvent, Modifiers, RepeatCount};
+use crate::cmd_runner::aliases;
+
+/// Settings applied when the line editor is created
+pub struct Settings {
+    pub edit_mode: EditMode,
+    pub bell_style: BellStyle,
+    pub completion_type: CompletionType,
+    pub history_size: usize,
+    pub history_ignore_dups: bool,
+}
+
+lazy_static! {
+    pub static ref SETTINGS: Mutex<Settings> = Mutex::new(Settings {
+        edit_mode: EditMode::Emacs,
+        bell_style: BellStyle::default(),
+        completion_type: CompletionType::List,
+        history_size: 1000,
+        history_ignore_dups: true,
+    });
+}
+
+/// What a bound key does
+pub enum Action {
+    Insert(String),
+    Call(FnPtr),
+}
+
+thread_local! {
+    //Key bindings by key; FnPtr is not Send so they stay on the main thread
+    static BINDINGS: RefCell<HashMap<KeyEvent, Action>> = RefCell::new(HashMap::new());
+    //Keys bound since the editor last picked them up
+    static NEW_KEYS: RefCell<Vec<KeyEvent>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn parse_edit_mode(name: &str) -> Option<EditMode> {
+    match name {
+        "emacs" => Some(EditMode::Emacs),
+        "vi" => Some(EditMode::Vi),
+        _ => None,
+    }
+}
+
+pub fn parse_bell_style(name: &str) -> Option<BellStyle> {
+    match name {
+        "audible" => Some(BellStyle::Audible),
+        "visible" => Some(BellStyle::Visible),
+        "none" => Some(BellStyle::None),
+        _ => None,
+    }
+}
+
+pub fn parse_completion_type(name: &str) -> Option<CompletionType> {
+    match name {
+        "list" => Some(CompletionType::List),
+        "circular" => Some(CompletionType::Circular),
+        _ => None,
+    }
+}
+
+/// The rustyline config built from the settings
+pub fn config() -> Config {
+    let settings = SETTINGS.lock().unwrap();
+    let builder = Config::builder()
+        .edit_mode(settings.edit_mode)
+        .bell_style(settings.bell_style)
+        .completion_type(settings.completion_type);
+    let builder = builder.clone().max_history_size(settings.history_size).unwrap_or(builder);
+    let builder = builder.clone().history_ignore_dups(settings.history_ignore_dups).unwrap_or(builder);
+    builder.build()
+}
+
+/// Parses "ctrl-g", "alt-x", "ctrl-alt-k", "f5", "up", "tab", "x"...
+pub fn parse_key(name: &str) -> Option<KeyEvent> {
+    let lower = name.to_lowercase();
+    let mut mods = Modifiers::NONE;
+    let mut rest = lower.as_str();
+    loop {
+        if let Some(r) = rest.strip_prefix("ctrl-").or_else(|| rest.strip_prefix("c-")) {
+            mods |= Modifiers::CTRL;
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("alt-").or_else(|| rest.strip_prefix("m-")) {
+            mods |= Modifiers::ALT;
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("shift-") {
+            mods |= Modifiers::SHIFT;
+            rest = r;
+        } else {
+            break;
+        }
+    }
+
+    let code = match rest {
+        "tab" => KeyCode::Tab,
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "space" => KeyCode::Char(' '),
+        _ if rest.len() > 1 && rest.starts_with('f') => KeyCode::F(rest[1..].parse().ok().filter(|n| (1..=12).contains(n))?),
+        _ => {
+            // A single character keeps its case unless Ctrl is held
+            let original = &name[name.len() - rest.len()..];
+            let mut chars = original.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+            return Some(KeyEvent::normalize(KeyEvent::new(c, mods)));
+        }
+    };
+    Some(KeyEvent::normalize(KeyEvent(code, mods)))
+}
+
+pub fn bind(key: KeyEvent, action: Action) {
+    BINDINGS.with(|b| b.borrow_mut().insert(key, action));
+    NEW_KEYS.with(|k| k.borrow_mut().push(key));
+}
+
+/// Keys bound since the last call, for the editor to hook up
+pub fn take_new() -> Vec<KeyEvent> {
+    NEW_KEYS.with(|k| std::mem::take(&mut *k.borrow_mut()))
+}
+
+/// Runs the action bound to the pressed key
+pub struct Binding;
+
+impl ConditionalEventHandler for Binding {
+    fn handle(&self, evt: &Event, _n: RepeatCount, _positive: bool, _ctx: &EventContext) -> Option<Cmd> {
+        let key = evt.get(0).copied()?;
+        let callback = BINDINGS.with(|b| match b.borrow().get(&key) {
+            Some(Action::Insert(text)) => Err(text.clone()),
+            Some(Action::Call(callback)) => Ok(callback.clone()),
+            None => Err(String::new()),
+        });
+        let text = match callback {
+            Err(text) => text,
+            // A closure may return text to insert
+            Ok(callback) => {
+                let result = crate::GLOBAL_ENGINE.with(|eng| {
+                    crate::GLOBAL_AST.with(|cell| {
+                        let empty = AST::empty();
+                        let ast = cell.borrow();
+                        callback.call::<Dynamic>(&eng.borrow(), ast.as_ref().unwrap_or(&empty), ())
+                    })
+                });
+                match result {
+                    Ok(value) => value.into_string().unwrap_or_default(),
+                    Err(e) => {
+                        eprintln!("\nError in key binding: {}", e);
+                        String::new()
+                    }
+                }
+            }
+        };
+        if text.is_empty() {
+            Some(Cmd::Repaint)
+        } else {
+            Some(Cmd::Insert(1, text))
+        }
+    }
+}
+
+/// Space after an abbreviation in command position replaces it with its expansion.
+/// Completion does the replacing: Cmd::Replace would leave the cursor before the
+/// expansion and, in emacs mode, kill only one character
+pub struct ExpandAbbreviation;
+
+impl ConditionalEventHandler for ExpandAbbreviation {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _positive: bool, ctx: &EventContext) -> Option<Cmd> {
+        aliases::abbreviation_at(ctx.line(), ctx.pos())?;
+        Some(Cmd::Complete)
+    }
+}
@@ -0,0 +1,334 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Prompt: the prompt() hook or PS1 with bash-style escapes
+use std::cell::RefCell;
+use std::env;
+use std::ffi::CStr;
+use std::io::Write;
+use std::time::Duration;
+use chrono::Local;
+use unicode_width::UnicodeWidthStr;
+use crate::api;
+use crate::cmd_runner::options;
+use super::segments;
+
+/// Used when there is neither a prompt() hook nor PS1
+pub const DEFAULT_PROMPT: &str = "Fix config>> ";
+
+/// Marks around text that takes no room on screen, what \[ and \] turn into
+const IGNORE_START: char = '\x01';
+const IGNORE_END: char = '\x02';
+
+/// How long a new prompt waits for async segments before it is drawn without them
+const SEGMENT_WAIT: Duration = Duration::from_millis(50);
+
+/// The prompt of the line being edited
+#[derive(Default)]
+struct Current {
+    //What the editor measures and what is shown
+    plain: String,
+    styled: String,
+    //Right prompt as shown and its width on screen
+    right: String,
+    right_width: usize,
+    //False while the typed text or the suggestion would reach the right prompt
+    right_shown: bool,
+    //Width of the typed text and the suggestion, None while the input has several lines
+    text_width: Option<usize>,
+    //Line and cursor position at the last redraw
+    drawn: Option<(String, usize)>,
+    //The next draw starts one row up, see segments::take_redraw
+    move_up: bool,
+    //Async segment results the prompt was built with
+    generation: usize,
+}
+
+impl Current {
+    /// True if the right prompt fits on the row next to the prompt and the text
+    fn right_fits(&self) -> bool {
+        self.text_width.is_some_and(|width| {
+            last_line_width(&self.plain) + width + 1 + self.right_width < term_width()
+        })
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Current> = RefCell::new(Current::default());
+}
+
+pub fn term_width() -> usize {
+    unsafe {
+        let mut ws: libc::winsize = std::mem::zeroed();
+        if libc::ioctl(1, libc::TIOCGWINSZ, &mut ws) == 0 && ws.ws_col > 0 {
+            ws.ws_col as usize
+        } else {
+            80
+        }
+    }
+}
+
+/// Width of the last line of the plain prompt
+fn last_line_width(plain: &str) -> usize {
+    plain.rsplit('\n').next().unwrap_or_default().width()
+}
+
+fn hostname() -> String {
+    let mut buf = [0 as libc::c_char; 256];
+    unsafe {
+        if libc::gethostname(buf.as_mut_ptr(), buf.len()) != 0 {
+            return String::new();
+        }
+        CStr::from_ptr(buf.as_ptr()).to_string_lossy().into_owned()
+    }
+}
+
+/// Current directory with $HOME shortened to ~
+fn short_cwd() -> String {
+    let cwd = api::get_current_dir();
+    match env::var("HOME") {
+        Ok(home) if !home.is_empty() && (cwd == home || cwd.starts_with(&format!("{}/", home))) => {
+            format!("~{}", &cwd[home.len()..])
+        }
+        _ => cwd,
+    }
+}
+
+/// Expands \u \h \H \w \W \t \T \A \@ \d \$ \n \e \a \s \\ \[ \]
+pub fn expand_ps1(ps1: &str) -> String {
+    let mut out = String::new();
+    let mut chars = ps1.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let now = Local::now();
+        match chars.next() {
+            Some('u') => out.push_str(&api::get_user()),
+            Some('h') => out.push_str(hostname().split('.').next().unwrap_or_default()),
+            Some('H') => out.push_str(&hostname()),
+            Some('w') => out.push_str(&short_cwd()),
+            Some('W') => {
+                let cwd = short_cwd();
+                out.push_str(if cwd == "~" || cwd == "/" { &cwd } else { cwd.rsplit('/').next().unwrap_or_default() });
+            }
+            Some('t') => out.push_str(&now.format("%H:%M:%S").to_string()),
+            Some('T') => out.push_str(&now.format("%I:%M:%S").to_string()),
+            Some('A') => out.push_str(&now.format("%H:%M").to_string()),
+            Some('@') => out.push_str(&now.format("%I:%M %p").to_string()),
+            Some('d') => out.push_str(&now.format("%a %b %d").to_string()),
+            Some('$') => out.push(if unsafe { libc::geteuid() } == 0 { '#' } else { '$' }),
+            Some('n') => out.push('\n'),
+            Some('e') => out.push('\x1b'),
+            Some('a') => out.push('\x07'),
+            Some('s') => out.push_str("sbsh"),
+            Some('[') => out.push(IGNORE_START),
+            Some(']') => out.push(IGNORE_END),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Removes ANSI escape sequences (CSI, OSC and two-byte ones)
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: ESC [ parameters final byte
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: ESC ] ... BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Splits a prompt into what the editor measures (no escapes, no \[ \] parts)
+/// and what the terminal gets (markers removed)
+fn split(prompt: &str) -> (String, String) {
+    let mut visible = String::new();
+    let mut styled = String::new();
+    let mut hidden = false;
+    for c in prompt.chars() {
+        match c {
+            IGNORE_START => hidden = true,
+            IGNORE_END => hidden = false,
+            _ => {
+                styled.push(c);
+                if !hidden {
+                    visible.push(c);
+                }
+            }
+        }
+    }
+    (strip_ansi(&visible), styled)
+}
+
+/// Result of a prompt hook (prompt, rprompt, transient_prompt), None if there is no such function
+fn from_hook(name: &str) -> Option<String> {
+    crate::GLOBAL_ENGINE.with(|eng| {
+        crate::GLOBAL_AST.with(|cell| {
+            let ast = cell.borrow();
+            let ast = ast.as_ref()?;
+            let mut scope = rhai::Scope::new();
+            match eng.borrow().call_fn_with_options::<rhai::Dynamic>(crate::rhai_api::hook_options(), &mut scope, ast, name, ()) {
+                Ok(value) => Some(value.to_string()),
+                Err(e) => {
+                    if crate::rhai_api::is_missing_hook(&e, name) {
+                        return None;
+                    }
+                    eprintln!("Error in {} hook: {}", name, e);
+                    Some(String::new())
+                }
+            }
+        })
+    })
+}
+
+/// True if the config can produce a prompt
+pub fn is_configured() -> bool {
+    let has_hook = crate::GLOBAL_AST.with(|cell| {
+        cell.borrow().as_ref().is_some_and(|ast| ast.iter_functions().any(|f| f.name == "prompt"))
+    });
+    has_hook || env::var("PS1").is_ok()
+}
+
+/// Runs the prompt hooks (or expands PS1/RPS1): plain prompt, styled prompt,
+/// styled right prompt and its width
+fn build() -> (String, String, String, usize) {
+    let prompt = from_hook("prompt")
+        .or_else(|| env::var("PS1").ok().map(|ps1| expand_ps1(&ps1)))
+        .unwrap_or_else(|| DEFAULT_PROMPT.to_string());
+    let right = from_hook("rprompt")
+        .or_else(|| env::var("RPS1").or_else(|_| env::var("RPROMPT")).ok().map(|rps1| expand_ps1(&rps1)))
+        .unwrap_or_default();
+
+    let (plain, styled) = split(&prompt);
+    let (right_plain, right) = split(&right);
+    // The right prompt must stay on one line
+    if right_plain.contains('\n') {
+        return (plain, styled, String::new(), 0);
+    }
+    (plain, styled, right, right_plain.width())
+}
+
+/// Builds the prompt for the next line and returns the plain text to give to the editor;
+/// the styled version is shown through highlight_prompt
+pub fn render() -> String {
+    segments::next_prompt();
+    let mut generation = segments::generation();
+    let (mut plain, mut styled, mut right, mut right_width) = build();
+    // Fast segments are shown right away instead of being redrawn
+    segments::wait(SEGMENT_WAIT);
+    if segments::generation() != generation {
+        generation = segments::generation();
+        (plain, styled, right, right_width) = build();
+    }
+    CURRENT.with(|c| {
+        let mut current = Current { plain: plain.clone(), styled, right, right_width, text_width: Some(0), generation, ..Current::default() };
+        current.right_shown = current.right_fits();
+        *c.borrow_mut() = current;
+    });
+    plain
+}
+
+/// Builds the prompt again when async segments have new results. The editor keeps the size
+/// of the prompt it was given, so a left prompt of another size waits for the next line
+fn update() {
+    let generation = segments::generation();
+    if CURRENT.with(|c| c.borrow().generation) == generation {
+        return;
+    }
+    let (plain, styled, right, right_width) = build();
+    CURRENT.with(|c| {
+        let mut current = c.borrow_mut();
+        current.generation = generation;
+        if plain.matches('\n').count() == current.plain.matches('\n').count()
+            && last_line_width(&plain) == last_line_width(&current.plain) {
+            current.styled = styled;
+        }
+        current.right = right;
+        current.right_width = right_width;
+        current.right_shown = current.right_fits();
+    });
+}
+
+/// The styled prompt if `plain` is the one rendered last, followed by the right prompt.
+/// The right prompt is drawn at the right edge of the row the input starts on and the
+/// cursor is put back, so the editor never counts it
+pub fn styled(plain: &str) -> Option<String> {
+    update();
+    CURRENT.with(|c| {
+        let mut current = c.borrow_mut();
+        if current.plain != plain {
+            return None;
+        }
+        let up = if std::mem::take(&mut current.move_up) { "\x1b[A" } else { "" };
+        if !current.right_shown || current.right_width == 0 {
+            return Some(format!("{}{}", up, current.styled));
+        }
+        Some(format!("{}{}\x1b7\x1b[{}G{}\x1b8", up, current.styled, term_width().saturating_sub(current.right_width), current.right))
+    })
+}
+
+/// Called by the hinter, which the editor asks before each redraw. Hides the right prompt
+/// while the input line and its suggestion would run into it, and tells a redraw asked for
+/// by a prompt segment (the line is unchanged) from one after a key
+pub fn set_text(line: &str, pos: usize, hint: &str) {
+    segments::set_reading(true);
+    CURRENT.with(|c| {
+        let mut current = c.borrow_mut();
+        current.text_width = (!line.contains('\n')).then(|| line.width() + hint.width());
+        current.right_shown = current.right_fits();
+        let unchanged = current.drawn.as_ref().is_some_and(|(drawn, at)| drawn == line && *at == pos);
+        current.move_up = unchanged && segments::take_redraw();
+        current.drawn = Some((line.to_string(), pos));
+    })
+}
+
+/// With the transientprompt option, redraws the submitted line with the compact prompt
+/// from transient_prompt() (default `\$ `) so the scrollback stays short
+pub fn make_transient(line: &str) {
+    if !options::get("transientprompt") || unsafe { libc::isatty(1) } == 0 || line.contains('\n') {
+        return;
+    }
+    let plain = CURRENT.with(|c| c.borrow().plain.clone());
+    // Rows taken by the prompt and the line; the cursor is on the row below them.
+    // Text that exactly fills a row does not start another one
+    let total = last_line_width(&plain) + line.width();
+    let rows = plain.matches('\n').count() + total.saturating_sub(1) / term_width() + 1;
+
+    let compact = from_hook("transient_prompt").unwrap_or_else(|| expand_ps1("\\$ "));
+    let (_, compact) = split(&compact);
+    print!("\x1b[{}A\r\x1b[J{}{}\r\n", rows, compact, super::highlight::highlight(line));
+    let _ = std::io::stdout().flush();
+}
@@ -0,0 +1,195 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Prompt segments computed on worker threads (git status, slow commands)
+//so a slow repository does not hold back the prompt
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use git2::Repository;
+use lazy_static::lazy_static;
+use rustyline::ExternalPrinter;
+use crate::api;
+use crate::cmd_runner::capture;
+
+#[derive(Clone, PartialEq)]
+pub struct GitStatus {
+    pub branch: String,
+    pub dirty: bool,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+/// What a git status was computed for: modification times of the index and HEAD
+type GitKey = (Option<SystemTime>, Option<SystemTime>);
+
+/// Edits to files that are not staged change neither the index nor HEAD,
+/// so a git status older than this is computed again
+const GIT_TTL: Duration = Duration::from_secs(2);
+
+lazy_static! {
+    //Last git status of each repository (by its .git directory)
+    static ref GIT: Mutex<HashMap<PathBuf, (GitKey, Instant, GitStatus)>> = Mutex::new(HashMap::new());
+    //Command segments by name
+    static ref COMMANDS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    //Last output of a command segment by name and directory, with the prompt it was run for
+    static ref OUTPUTS: Mutex<HashMap<(String, String), (usize, String)>> = Mutex::new(HashMap::new());
+    //Jobs running now
+    static ref RUNNING: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+    //Asks the line editor to redraw
+    static ref PRINTER: Mutex<Option<Box<dyn ExternalPrinter + Send>>> = Mutex::new(None);
+}
+
+//Bumped when a result arrives
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+//Bumped for each new prompt, command segments run once per prompt
+static PROMPT: AtomicUsize = AtomicUsize::new(0);
+//True from the first draw of a line until the editor returns it, prints go through it then
+static READING: AtomicBool = AtomicBool::new(false);
+//Set once the config uses an async segment
+static USED: AtomicBool = AtomicBool::new(false);
+//Redraws asked for and not yet drawn, see take_redraw
+static REDRAWS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn set_printer(printer: Option<Box<dyn ExternalPrinter + Send>>) {
+    *PRINTER.lock().unwrap() = printer;
+}
+
+pub fn has_printer() -> bool {
+    PRINTER.lock().unwrap().is_some()
+}
+
+/// True if the next line needs an external printer: the config uses async segments and
+/// no input is waiting. With a printer the editor reads keys typed ahead all at once but
+/// handles them only as more keys arrive, so a line that starts with typeahead has none
+pub fn printer_wanted() -> bool {
+    let mut fds = libc::pollfd { fd: 0, events: libc::POLLIN, revents: 0 };
+    USED.load(Ordering::SeqCst) && unsafe { libc::poll(&mut fds, 1, 0) } == 0
+}
+
+pub fn set_reading(reading: bool) {
+    READING.store(reading, Ordering::SeqCst);
+}
+
+pub fn generation() -> usize {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+pub fn next_prompt() {
+    PROMPT.fetch_add(1, Ordering::SeqCst);
+    REDRAWS.store(0, Ordering::SeqCst);
+}
+
+/// The editor redraws the line after an external print, which always ends with a newline.
+/// True once for each such redraw: the prompt then starts one row up, where it was before
+pub fn take_redraw() -> bool {
+    REDRAWS.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)).is_ok()
+}
+
+/// Waits up to `timeout` for running jobs
+pub fn wait(timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    while !RUNNING.lock().unwrap().is_empty() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+fn is_running(id: &str) -> bool {
+    RUNNING.lock().unwrap().contains(id)
+}
+
+/// Runs `job` on a worker thread unless a job with this id is already running;
+/// the editor redraws the line if the job returns true (its result changed)
+fn spawn(id: String, job: impl FnOnce() -> bool + Send + 'static) {
+    if !RUNNING.lock().unwrap().insert(id.clone()) {
+        return;
+    }
+    USED.store(true, Ordering::SeqCst);
+    thread::spawn(move || {
+        let changed = job();
+        RUNNING.lock().unwrap().remove(&id);
+        if !changed {
+            return;
+        }
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+        // An external print is the only way to make the editor redraw from another thread
+        if READING.load(Ordering::SeqCst)
+            && let Some(printer) = PRINTER.lock().unwrap().as_mut() {
+            REDRAWS.fetch_add(1, Ordering::SeqCst);
+            if printer.print("\n".to_string()).is_err() {
+                REDRAWS.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    });
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn compute_git(git_dir: &Path) -> Option<GitStatus> {
+    let repo = Repository::open(git_dir).ok()?;
+    let branch = repo.head().ok()
+        .and_then(|head| head.shorthand().map(str::to_string))
+        .unwrap_or_default();
+    let dirty = repo.statuses(None)
+        .map(|statuses| statuses.iter().any(|entry| entry.status() != git2::Status::CURRENT))
+        .unwrap_or(false);
+    let (ahead, behind) = api::ahead_behind(&repo);
+    Some(GitStatus { branch, dirty, ahead, behind })
+}
+
+/// Git status of the repository in the current directory. While it is being computed
+/// the last known status of the same repository is returned, None if there is none yet
+pub fn git_status() -> Option<GitStatus> {
+    let repo = Repository::discover(env::current_dir().ok()?).ok()?;
+    let git_dir = repo.path().to_path_buf();
+    let key = (modified(&git_dir.join("index")), modified(&git_dir.join("HEAD")));
+
+    let cached = GIT.lock().unwrap().get(&git_dir).cloned();
+    if cached.as_ref().is_none_or(|(cached_key, time, _)| *cached_key != key || time.elapsed() > GIT_TTL) {
+        let dir = git_dir.clone();
+        spawn(format!("git:{}", git_dir.display()), move || {
+            let Some(status) = compute_git(&dir) else {
+                return false;
+            };
+            let old = GIT.lock().unwrap().insert(dir, (key, Instant::now(), status.clone()));
+            old.is_none_or(|(_, _, old)| old != status)
+        });
+    }
+    cached.map(|(_, _, status)| status)
+}
+
+pub fn add_command(name: &str, command: &str) {
+    COMMANDS.lock().unwrap().insert(name.to_string(), command.to_string());
+}
+
+/// Output of a command segment in the current directory, run again for every prompt.
+/// While it runs the previous output is returned, None if there is none yet
+pub fn command_output(name: &str) -> Option<String> {
+    let command = COMMANDS.lock().unwrap().get(name).cloned()?;
+    let cwd = api::get_current_dir();
+    let prompt = PROMPT.load(Ordering::SeqCst);
+    let key = (name.to_string(), cwd.clone());
+
+    let cached = OUTPUTS.lock().unwrap().get(&key).cloned();
+    let id = format!("command:{}:{}", name, cwd);
+    if cached.as_ref().is_none_or(|(run_for, _)| *run_for != prompt) && !is_running(&id) {
+        let options = capture::Options { cwd: Some(cwd), ..Default::default() };
+        // Started here so that the command runs in a copy of the shell like any other
+        // line; only the waiting is left to the worker
+        if let Ok(child) = capture::start(&command, &options) {
+            spawn(id, move || {
+                let text = capture::finish(child, &options).out.trim_end().to_string();
+                let old = OUTPUTS.lock().unwrap().insert(key, (prompt, text.clone()));
+                old.is_none_or(|(_, old)| old != text)
+            });
+        }
+    }
+    cached.map(|(_, text)| text)
+}
@@ -0,0 +1,70 @@
+// Copyright (C) <2026> <Bogdan Yachmenv>
+// SPDX-License-Identifier: GPL-3.0/
+
+//Fish-style autosuggestions from history
+use rhai::Dynamic;
+use rustyline::{Cmd, ConditionalEventHandler, Event, EventContext, RepeatCount};
+use crate::api;
+use crate::cmd_runner::history;
+
+/// Most recent history entry that extends `line`, preferring the ones run in the current directory
+fn from_history(line: &str) -> Option<String> {
+    let cwd = api::get_current_dir();
+    history::with_entries(|entries| {
+        let extends = |command: &str| command.len() > line.len() && command.starts_with(line);
+        entries.iter().rev()
+            .find(|e| e.cwd == cwd && extends(&e.command))
+            .or_else(|| entries.iter().rev().find(|e| extends(&e.command)))
+            .map(|e| e.command.clone())
+    })
+}
+
+/// Lets the on_suggest(line, suggestion) hook replace the suggestion;
+/// returning "" or () hides it
+fn run_hook(line: &str, suggestion: Option<String>) -> Option<String> {
+    let result = crate::GLOBAL_ENGINE.with(|eng| {
+        crate::GLOBAL_AST.with(|cell| {
+            let ast = cell.borrow();
+            let ast = ast.as_ref()?;
+            let mut scope = rhai::Scope::new();
+            let args = (line.to_string(), suggestion.clone().unwrap_or_default());
+            match eng.borrow().call_fn_with_options::<Dynamic>(crate::rhai_api::hook_options(), &mut scope, ast, "on_suggest", args) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    if !crate::rhai_api::is_missing_hook(&e, "on_suggest") {
+                        eprintln!("\nError in on_suggest hook: {}", e);
+                    }
+                    None
+                }
+            }
+        })
+    });
+    match result {
+        Some(value) => value.into_string().ok(),
+        None => suggestion,
+    }
+}
+
+/// The part of a suggested line that is not typed yet
+pub fn hint(line: &str, pos: usize) -> Option<String> {
+    // Only at the end of a non-empty line
+    if line.trim().is_empty() || pos < line.len() {
+        return None;
+    }
+    run_hook(line, from_history(line))
+        .filter(|s| s.len() > line.len() && s.starts_with(line))
+        .map(|s| s[line.len()..].to_string())
+}
+
+/// Ctrl-F accepts the suggestion at the end of the line and moves forward otherwise
+pub struct AcceptSuggestion;
+
+impl ConditionalEventHandler for AcceptSuggestion {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _positive: bool, ctx: &EventContext) -> Option<Cmd> {
+        if ctx.has_hint() && ctx.pos() == ctx.line().len() {
+            Some(Cmd::CompleteHint)
+        } else {
+            None
+        }
+    }
+}
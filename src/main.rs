@@ -1,9 +1,11 @@
 // Copyright (C) <2026> <Bogdan Yachmenv>
 // SPDX-License-Identifier: GPL-3.0
 
-use rustyline::DefaultEditor;
+use rustyline::{Editor, EventHandler, KeyEvent};
 use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
 mod api;
+mod editor;
 mod rhai_api;
 use std::cell::RefCell;
 use rhai::{Engine, AST};
@@ -11,13 +13,12 @@ pub mod cmd_runner;
 
 thread_local! {
     pub static GLOBAL_ENGINE: RefCell<Engine> = RefCell::new(rhai_api::init_rhai());
-    pub static GLOBAL_AST: RefCell<Option<AST>> = RefCell::new(None);
+    pub static GLOBAL_AST: RefCell<Option<AST>> = const { RefCell::new(None) };
 }
 
 fn main() {
     let home = api::get_var("HOME".to_string()).unwrap_or_default();
-    let config = home.clone() + "/.sbshrc.rhai";
-    let history = home + "/.sbsh_history.txt";
+    let config = home + "/.sbshrc.rhai";
 
     // Compiling config – если ошибка, продолжаем без AST
     let ast = GLOBAL_ENGINE.with(|eng| {
@@ -45,13 +46,11 @@ fn main() {
     }
 
     // Получаем PS1 или ставим запасной промпт
-    let ps1_default = "Fix config>> ".to_string();
-    let ps1 = api::get_var("PS1".to_string()).unwrap_or_else(|| {
-        eprintln!("Warning: PS1 not set. Using default prompt: {}", ps1_default);
-        ps1_default.clone()
-    });
+    if !editor::prompt::is_configured() {
+        eprintln!("Warning: neither prompt() nor PS1 is set. Using default prompt: {}", editor::prompt::DEFAULT_PROMPT);
+    }
 
-    let mut rl = match DefaultEditor::new() {
+    let mut rl = match Editor::<editor::ShellHelper, DefaultHistory>::with_config(editor::keys::config()) {
         Ok(editor) => editor,
         Err(e) => {
             eprintln!("Warning: Failed to create rustyline editor: {}. Using minimal input.", e);
@@ -61,31 +60,50 @@ fn main() {
             panic!("Cannot proceed without rustyline");
         }
     };
-    let _ = rl.load_history(&history);
+    rl.set_helper(Some(editor::ShellHelper));
+    rl.bind_sequence(KeyEvent::ctrl('F'), EventHandler::Conditional(Box::new(editor::suggest::AcceptSuggestion)));
+    rl.bind_sequence(KeyEvent::ctrl('R'), EventHandler::Conditional(Box::new(editor::fuzzy::HistorySearch)));
+    rl.bind_sequence(KeyEvent::ctrl('T'), EventHandler::Conditional(Box::new(editor::fuzzy::FileSearch)));
+    rl.bind_sequence(KeyEvent::alt('c'), EventHandler::Conditional(Box::new(editor::fuzzy::DirSearch)));
+    rl.bind_sequence(KeyEvent::from(' '), EventHandler::Conditional(Box::new(editor::keys::ExpandAbbreviation)));
+    for command in cmd_runner::history::load() {
+        rl.add_history_entry(command).ok();
+    }
 
+    // Text the next input line starts with (expanded history with histverify)
+    let mut initial = String::new();
     loop {
+        // Keys bound with bind_key in the config or since the last line
+        for key in editor::keys::take_new() {
+            rl.bind_sequence(key, EventHandler::Conditional(Box::new(editor::keys::Binding)));
+        }
+
         let mut scope = ::rhai::Scope::new();
 
         // Run hook repeat
         GLOBAL_ENGINE.with(|eng| {
             GLOBAL_AST.with(|cell| {
-                if let Some(ast) = cell.borrow().as_ref() {
-                    if let Err(e) = eng.borrow().call_fn::<()>(&mut scope, ast, "repeat", ()) {
-                        if !e.to_string().contains("Function not found") {
-                            eprintln!("Error in repeat hook: {}", e);
-                        }
-                    }
+                if let Some(ast) = cell.borrow().as_ref()
+                    && let Err(e) = eng.borrow().call_fn_with_options::<()>(rhai_api::hook_options(), &mut scope, ast, "repeat", ())
+                    && !rhai_api::is_missing_hook(&e, "repeat") {
+                    eprintln!("Error in repeat hook: {}", e);
                 }
             });
         });
 
-        // Получаем актуальный PS1 (может измениться в хуке)
-        let ps1 = api::get_var("PS1".to_string()).unwrap_or_else(|| {
-            eprintln!("Warning: PS1 became unset, using default.");
-            "Fix config>> ".to_string()
-        });
+        // Prompt from the prompt() hook or PS1 (may be changed by the hook)
+        let prompt = editor::prompt::render();
+        // Lets async prompt segments redraw the line when they finish
+        if !editor::segments::printer_wanted() {
+            editor::segments::set_printer(None);
+        } else if !editor::segments::has_printer()
+            && let Ok(printer) = rl.create_external_printer() {
+            editor::segments::set_printer(Some(Box::new(printer)));
+        }
 
-        let line = match rl.readline(&ps1) {
+        let result = rl.readline_with_initial(&prompt, (&initial, ""));
+        editor::segments::set_reading(false);
+        let line = match result {
             Ok(line) => line,
             Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
             Err(err) => {
@@ -93,26 +111,57 @@ fn main() {
                 break;
             }
         };
-        rl.add_history_entry(&line).ok();
+        initial.clear();
+        // An abbreviation typed last is expanded on Enter as on Space
+        let line = cmd_runner::aliases::expand_abbreviation(&line);
+        editor::prompt::make_transient(&line);
+
+        // History expansion: !!, !$, ^old^new...
+        let line = match api::expand_history(&line) {
+            Ok(Some(expanded)) if api::get_option("histverify".to_string()) => {
+                // Let the user check the expanded line before running it
+                if let Some(entry) = cmd_runner::history::filter(&line) {
+                    rl.add_history_entry(entry).ok();
+                }
+                initial = expanded;
+                continue;
+            }
+            Ok(Some(expanded)) => {
+                println!("{}", expanded);
+                expanded
+            }
+            Ok(None) => line,
+            Err(e) => {
+                eprintln!("sbsh: {}", e);
+                if let Some(entry) = cmd_runner::history::filter(&line) {
+                    rl.add_history_entry(entry).ok();
+                }
+                continue;
+            }
+        };
+        // What goes to history: filtered and with secrets redacted. It is saved before the
+        // command runs so that it is not lost if the shell is killed meanwhile
+        if let Some(entry) = cmd_runner::history::filter(&line) {
+            rl.add_history_entry(&entry).ok();
+            cmd_runner::history::add(&entry, &api::get_current_dir());
+        }
 
-        let mut scope = ::rhai::Scope::new();
+        // on_input may rewrite the line or swallow it; a swallowed line stays in history
+        let line = match rhai_api::on_input(&line) {
+            Some(line) if !line.trim().is_empty() => line,
+            _ => {
+                cmd_runner::history::finish(0);
+                continue;
+            }
+        };
 
-        // Run on_input hook
-        GLOBAL_ENGINE.with(|eng| {
-            GLOBAL_AST.with(|cell| {
-                if let Some(ast) = cell.borrow().as_ref() {
-                    if let Err(e) = eng.borrow().call_fn::<()>(&mut scope, ast, "on_input", (line.clone(),)) {
-                        if !e.to_string().contains("Function not found") {
-                            eprintln!("Error in on_input hook: {}", e);
-                        }
-                    }
-                }
-            });
+        // Executing command, with the preexec and precmd hooks around it
+        rhai_api::call_hook("preexec", (line.clone(),));
+        let last = cmd_runner::timing::measure(&line, || {
+            api::run_command(line.clone());
+            cmd_runner::last_status()
         });
-
-        // Executing command
-        api::run_command(line);
+        cmd_runner::history::finish(last.status);
+        rhai_api::call_hook("precmd", (last.status as i64, last.duration.as_secs_f64()));
     }
-
-    rl.save_history(&history).ok();
 }
\ No newline at end of file
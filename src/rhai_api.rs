@@ -2,9 +2,71 @@
 // SPDX-License-Identifier: GPL-3.0/
 
 ///Rhai integration
-use rhai::{Engine, Scope};
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs};
 use crate::api;
 
+/// Options for calling config hooks: the top-level code of the config already ran
+/// at startup and must not run again on every call
+pub fn hook_options() -> CallFnOptions<'static> {
+    CallFnOptions::new().eval_ast(false)
+}
+
+/// True if the error says the hook itself is not defined,
+/// not some function called from inside it
+pub fn is_missing_hook(e: &EvalAltResult, name: &str) -> bool {
+    matches!(e, EvalAltResult::ErrorFunctionNotFound(signature, _) if signature.split(' ').next() == Some(name))
+}
+
+/// Calls a hook that returns nothing; a missing hook is not an error
+pub fn call_hook(name: &str, args: impl FuncArgs) {
+    crate::GLOBAL_ENGINE.with(|eng| {
+        crate::GLOBAL_AST.with(|cell| {
+            let mut scope = rhai::Scope::new();
+            if let Some(ast) = cell.borrow().as_ref()
+                && let Err(e) = eng.borrow().call_fn_with_options::<Dynamic>(hook_options(), &mut scope, ast, name, args)
+                && !is_missing_hook(&e, name) {
+                eprintln!("Error in {} hook: {}", name, e);
+            }
+        });
+    });
+}
+
+/// Runs the on_input hook: a string replaces the line, () (or no hook) keeps it,
+/// false swallows it (None)
+pub fn on_input(line: &str) -> Option<String> {
+    let result = crate::GLOBAL_ENGINE.with(|eng| {
+        crate::GLOBAL_AST.with(|cell| {
+            let mut scope = rhai::Scope::new();
+            let ast = cell.borrow();
+            let ast = ast.as_ref()?;
+            match eng.borrow().call_fn_with_options::<Dynamic>(hook_options(), &mut scope, ast, "on_input", (line.to_string(),)) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    if !is_missing_hook(&e, "on_input") {
+                        eprintln!("Error in on_input hook: {}", e);
+                    }
+                    None
+                }
+            }
+        })
+    });
+    let Some(value) = result else {
+        return Some(line.to_string());
+    };
+    if value.is_string() {
+        return value.into_string().ok();
+    }
+    match value.as_bool() {
+        Ok(false) => None,
+        Ok(true) => Some(line.to_string()),
+        Err(_) if value.is_unit() => Some(line.to_string()),
+        Err(type_name) => {
+            eprintln!("on_input returned {}; expected a string, () or false", type_name);
+            Some(line.to_string())
+        }
+    }
+}
+
 pub fn init_rhai() -> Engine{
     let mut  engine = Engine::new();
     engine.register_fn("get_user", api::get_user);
@@ -17,14 +79,26 @@ pub fn init_rhai() -> Engine{
     engine.register_fn("alias_remove", api::alias_remove);
     engine.register_fn("alias_get", api::alias_get);
     engine.register_fn("alias_clear", api::alias_clear);
+    engine.register_fn("alias_add_global", api::alias_add_global);
+    engine.register_fn("alias_list_global", api::alias_list_global);
+    engine.register_fn("abbr_add", api::abbr_add);
+    engine.register_fn("abbr_get", api::abbr_get);
+    engine.register_fn("abbr_remove", api::abbr_remove);
+    engine.register_fn("abbr_list", api::abbr_list);
     engine.register_fn("set_color", api::set_color);
     engine.register_fn("set_bold", api::set_bold);
+    engine.register_fn("set_highlight", api::set_highlight);
     engine.register_fn("system", api::run_command);
+    engine.register_fn("run_command", api::capture_command);
+    engine.register_fn("run_command", api::capture_command_with);
     engine.register_fn("is_git_repo", api::is_git_repo);
     engine.register_fn("get_git_branch", api::get_git_branch);
     engine.register_fn("git_is_dirty", api::git_is_dirty);
     engine.register_fn("load_plugin", api::load_plugin);
     engine.register_fn("git_ahead_behind", api::git_ahead_behind);
+    engine.register_fn("git_status_async", api::git_status_async);
+    engine.register_fn("add_prompt_segment", api::add_prompt_segment);
+    engine.register_fn("prompt_segment", api::prompt_segment);
     engine.register_fn("read_file", api::read_file);
     engine.register_fn("write_file", api::write_file);
     engine.register_fn("is_file", api::is_file);
@@ -32,7 +106,39 @@ pub fn init_rhai() -> Engine{
     engine.register_fn("get_file", api::get_file);
     engine.register_fn("start_timer", api::start_timer);
     engine.register_fn("stop_timer", api::stop_timer);
+    engine.register_fn("start_timer", api::start_named_timer);
+    engine.register_fn("stop_timer", api::stop_named_timer);
+    engine.register_fn("lap_timer", api::lap_timer);
+    engine.register_fn("reset_timer", api::reset_timer);
+    engine.register_fn("list_timers", api::list_timers);
+    engine.register_fn("format_duration", api::format_duration);
+    engine.register_fn("format_duration", api::format_duration_int);
+    engine.register_fn("last_duration", api::last_duration);
+    engine.register_fn("last_cpu_time", api::last_cpu_time);
+    engine.register_fn("last_status", api::last_status);
+    engine.register_fn("last_command", api::last_command);
     engine.register_fn("get_time", api::get_current_time);
+    engine.register_fn("set_option", api::set_option);
+    engine.register_fn("get_option", api::get_option);
+    engine.register_fn("prompt_input", api::prompt_input);
+    engine.register_fn("dir_jump", api::dir_jump);
+    engine.register_fn("add_history_secret", api::add_history_secret);
+    engine.register_fn("clear_history_secrets", api::clear_history_secrets);
+    engine.register_fn("complete", api::complete);
+    engine.register_fn("complete_files", api::complete_files);
+    engine.register_fn("complete_dirs", api::complete_dirs);
+    engine.register_fn("complete_options", api::complete_options);
+    engine.register_fn("complete_cache", api::complete_cache);
+    engine.register_fn("fuzzy_select", api::fuzzy_select);
+    engine.register_fn("set_edit_mode", api::set_edit_mode);
+    engine.register_fn("set_bell_style", api::set_bell_style);
+    engine.register_fn("set_completion_type", api::set_completion_type);
+    engine.register_fn("set_history_size", api::set_history_size);
+    engine.register_fn("set_history_ignore_dups", api::set_history_ignore_dups);
+    engine.register_fn("bind_key", api::bind_key);
+    engine.register_fn("bind_key", api::bind_key_text);
+    engine.register_fn("history_list", api::history_list);
+    engine.register_fn("file_list", api::file_list);
     //Returned engine for work in main func
     engine
 }
\ No newline at end of file